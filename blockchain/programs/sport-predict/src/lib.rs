@@ -1,359 +1,3011 @@
 use anchor_lang::prelude::*;
 use anchor_spl::token::{self, Token, TokenAccount, Transfer};
 
+pub mod math;
+
 declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
 
+// Backend match ids are cuids; the id is also used as a PDA seed, so it must fit in 32 bytes
+pub const MAX_MATCH_ID_LEN: usize = 32;
+pub const MAX_NAME_LEN: usize = 64;
+pub const MAX_DESCRIPTION_LEN: usize = 256;
+// Usernames double as PDA seeds, so they must fit in a single 32-byte seed
+pub const MIN_USERNAME_LEN: usize = 3;
+pub const MAX_USERNAME_LEN: usize = 32;
+pub const MAX_OUTCOMES: usize = 32;
+pub const MAX_ROLE_GRANTS: usize = 32;
+
+// Bits of `Platform.pause_flags`; each halts one group of instructions
+pub const PAUSE_DEPOSITS: u8 = 1 << 0;
+pub const PAUSE_WITHDRAWALS: u8 = 1 << 1;
+pub const PAUSE_BETTING: u8 = 1 << 2;
+pub const PAUSE_SETTLEMENT: u8 = 1 << 3;
+pub const PAUSE_ETF: u8 = 1 << 4;
+pub const PAUSE_ALL: u8 =
+    PAUSE_DEPOSITS | PAUSE_WITHDRAWALS | PAUSE_BETTING | PAUSE_SETTLEMENT | PAUSE_ETF;
+pub const DEFAULT_DISPUTE_PERIOD: i64 = 2 * 60 * 60;
+// 100 tokens at 6 decimals; a free challenge would let anyone freeze claims on every market
+pub const DEFAULT_CHALLENGE_BOND: u64 = 100_000_000;
+// Shortest delay a scheduled change can set; the delay given at initialize may be lower so local
+// deployments can configure themselves without waiting
+pub const MIN_TIMELOCK_DELAY: i64 = 24 * 60 * 60;
+pub const DEFAULT_BET_CANCEL_CUTOFF: i64 = 15 * 60;
+// Seconds in-play odds stay valid for cash-out quotes before the odds setter must refresh them
+pub const DEFAULT_MAX_ODDS_AGE: i64 = 60;
+pub const MAX_PARLAY_LEGS: usize = 8;
+// Seconds an LP must wait after depositing before redeeming shares, so liquidity can't be parked
+// around a single result
+pub const MIN_LP_HOLD_PERIOD: i64 = 24 * 60 * 60;
+// Regions are ISO 3166 codes, either a country (CN) or a subdivision (IN-AP), stored as upper-case
+// ASCII zero-padded to the longest subdivision code. Account fields spell the array out as [u8; 6].
+pub const REGION_CODE_LEN: usize = 6;
+pub type RegionCode = [u8; REGION_CODE_LEN];
+// A holder's country plus, where a restriction depends on it, their subdivision
+pub const MAX_ATTESTED_REGIONS: usize = 4;
+pub const MAX_BLOCKED_REGIONS: usize = 96;
+// Blocks a single market or ETF adds on top of the platform default
+pub const MAX_EXTRA_BLOCKED_REGIONS: usize = 16;
+// Initial platform default for the regions blocked on every market and ETF; mirrors the restricted
+// list in LegalityCheck_KYC/docs/RESTRICTED_REGIONS.md
+pub const DEFAULT_BLOCKED_REGIONS: [&str; 58] = [
+    "CN", "AF", "IN-AP", "IN-AR", "BY", "BE", "BA", "BG", "CD", "CI", "HR", "CU", "CY", "CZ", "EG",
+    "EE", "FR", "GF", "PF", "TF", "GR", "HU", "ID", "IR", "IQ", "IT", "JP", "IN-KL", "XK", "LV",
+    "LT", "MY", "MT", "ME", "MM", "IN-NL", "NG", "KP", "MK", "IN-OR", "PK", "PL", "PT", "RO", "RU",
+    "RS", "IN-SK", "SK", "SI", "SD", "SY", "IN-TN", "IN-TG", "TR", "UA", "VN", "YU", "ZW",
+];
+// Raised or removed responsible-gambling limits only apply after this delay; lowered ones apply at once
+pub const LIMIT_INCREASE_COOLING_OFF: i64 = 24 * 60 * 60;
+
 #[program]
 pub mod sport_predict {
     use super::*;
 
     // Initialize the platform
-    pub fn initialize(ctx: Context<Initialize>) -> Result<()> {
+    pub fn initialize(ctx: Context<Initialize>, timelock_delay: i64) -> Result<()> {
+        require!(timelock_delay >= 0, SportPredictError::InvalidTimelockDelay);
         let platform = &mut ctx.accounts.platform;
         platform.authority = ctx.accounts.authority.key();
+        platform.pending_authority = None;
+        platform.min_kyc_level = 0;
+        platform.timelock_delay = timelock_delay;
+        platform.change_count = 0;
+        platform.dispute_period = DEFAULT_DISPUTE_PERIOD;
+        platform.challenge_bond = DEFAULT_CHALLENGE_BOND;
+        platform.total_slashed_bonds = 0;
+        platform.bet_cancel_cutoff = DEFAULT_BET_CANCEL_CUTOFF;
+        platform.cancellation_fee_bps = 0;
+        platform.cash_out_margin_bps = 0;
+        platform.max_odds_age = DEFAULT_MAX_ODDS_AGE;
+        // Betting stays closed until LPs fund the house and exposure limits are configured
+        platform.house_reserve = 0;
+        platform.winnings_payable = 0;
+        platform.total_lp_shares = 0;
+        platform.total_exposure = 0;
+        platform.max_market_exposure = 0;
+        platform.max_platform_exposure = 0;
+        platform.winnings_fee_bps = 0;
+        platform.deposit_fee_bps = 0;
+        platform.withdrawal_fee_bps = 0;
+        platform.default_blocked_regions = DEFAULT_BLOCKED_REGIONS
+            .iter()
+            .map(|code| Attestation::parse_region(code))
+            .collect::<Result<_>>()?;
         platform.bump = *ctx.bumps.get("platform").unwrap();
         platform.total_users = 0;
         platform.total_bets = 0;
         platform.total_volume = 0;
+
+        let fee_ledger = &mut ctx.accounts.fee_ledger;
+        fee_ledger.accrued_fees = 0;
+        fee_ledger.total_collected = 0;
+        fee_ledger.total_withdrawn = 0;
+        fee_ledger.bump = *ctx.bumps.get("fee_ledger").unwrap();
+
+        // The initializing authority bootstraps the role registry as its first admin
+        let roles = &mut ctx.accounts.roles;
+        roles.grants = vec![RoleGrant {
+            holder: ctx.accounts.authority.key(),
+            role: Role::Admin,
+        }];
+        roles.oracle_quorum = 0;
+        roles.bump = *ctx.bumps.get("roles").unwrap();
         Ok(())
     }
 
-    // Create user account
-    pub fn create_user(ctx: Context<CreateUser>, username: String) -> Result<()> {
+    // Revoke a role. Unlike grants this takes effect immediately so a compromised key can be cut off.
+    // The last admin cannot be removed; removing an oracle lowers the quorum if fewer oracles remain.
+    pub fn revoke_role(ctx: Context<ManageRoles>, holder: Pubkey, role: Role) -> Result<()> {
+        let roles = &mut ctx.accounts.roles;
+        let previous_quorum = roles.oracle_quorum;
+        roles.revoke(holder, role)?;
+        let now = Clock::get()?.unix_timestamp;
+
+        emit!(RoleUpdatedEvent {
+            holder,
+            role,
+            granted: false,
+            by: ctx.accounts.authority.key(),
+            timestamp: now,
+        });
+        if roles.oracle_quorum != previous_quorum {
+            emit!(OracleQuorumLoweredEvent {
+                previous_quorum,
+                quorum: roles.oracle_quorum,
+                timestamp: now,
+            });
+        }
+
+        Ok(())
+    }
+
+    // Freeze or unfreeze a user account
+    pub fn set_user_active(ctx: Context<SetUserActive>, is_active: bool) -> Result<()> {
         let user = &mut ctx.accounts.user;
+        user.is_active = is_active;
+
+        emit!(UserStatusChangedEvent {
+            user: user.key(),
+            is_active,
+            by: ctx.accounts.compliance_officer.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Nominate a new platform authority. Takes effect once the nominee accepts; None withdraws it.
+    pub fn propose_authority(
+        ctx: Context<ProposeAuthority>,
+        new_authority: Option<Pubkey>,
+    ) -> Result<()> {
         let platform = &mut ctx.accounts.platform;
+        platform.pending_authority = new_authority;
 
-        user.authority = ctx.accounts.authority.key();
-        user.username = username;
-        user.bump = *ctx.bumps.get("user").unwrap();
-        user.balance = 0;
-        user.total_bets = 0;
-        user.total_wins = 0;
-        user.total_volume = 0;
-        user.is_active = true;
+        emit!(AuthorityProposedEvent {
+            authority: platform.authority,
+            pending_authority: new_authority,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
 
-        platform.total_users += 1;
+        Ok(())
+    }
+
+    // Complete an authority transfer. Must be signed by the nominated key. The admin role moves with
+    // the authority so the outgoing key keeps no administrative access.
+    pub fn accept_authority(ctx: Context<AcceptAuthority>) -> Result<()> {
+        let platform = &mut ctx.accounts.platform;
+        let roles = &mut ctx.accounts.roles;
+        let previous = platform.authority;
+        let authority = ctx.accounts.new_authority.key();
+        let now = Clock::get()?.unix_timestamp;
+
+        platform.authority = authority;
+        platform.pending_authority = None;
+
+        if !roles.has_role(&authority, Role::Admin) {
+            roles.grant(authority, Role::Admin)?;
+            emit!(RoleUpdatedEvent {
+                holder: authority,
+                role: Role::Admin,
+                granted: true,
+                by: authority,
+                timestamp: now,
+            });
+        }
+        if previous != authority && roles.has_role(&previous, Role::Admin) {
+            roles.revoke(previous, Role::Admin)?;
+            emit!(RoleUpdatedEvent {
+                holder: previous,
+                role: Role::Admin,
+                granted: false,
+                by: authority,
+                timestamp: now,
+            });
+        }
+
+        emit!(AuthorityTransferredEvent {
+            previous,
+            authority,
+            timestamp: now,
+        });
 
         Ok(())
     }
 
-    // Deposit funds
-    pub fn deposit(ctx: Context<Deposit>, amount: u64) -> Result<()> {
-        let user = &mut ctx.accounts.user;
+    // Queue a sensitive parameter change (fees, limits, oracle set, role grants). It can be executed
+    // once the platform's timelock delay has passed and cancelled by the scheduling role until then.
+    pub fn schedule_change(ctx: Context<ScheduleChange>, change: ParamChange) -> Result<()> {
+        change.validate()?;
+
         let platform = &mut ctx.accounts.platform;
+        let pending = &mut ctx.accounts.pending_change;
+        let now = Clock::get()?.unix_timestamp;
 
-        // Transfer tokens from user to platform vault
-        let transfer_ctx = CpiContext::new(
+        pending.id = platform.change_count;
+        pending.change = change.clone();
+        pending.proposer = ctx.accounts.proposer.key();
+        pending.scheduled_at = now;
+        pending.eta = math::add_seconds(now, platform.timelock_delay)?;
+        pending.bump = *ctx.bumps.get("pending_change").unwrap();
+
+        platform.change_count = math::add(platform.change_count, 1)?;
+
+        emit!(ChangeScheduledEvent {
+            id: pending.id,
+            change,
+            proposer: pending.proposer,
+            eta: pending.eta,
+        });
+
+        Ok(())
+    }
+
+    // Apply a scheduled change once its delay has elapsed. Permissionless; rent goes back to the proposer.
+    pub fn execute_change(ctx: Context<ExecuteChange>) -> Result<()> {
+        let pending = &ctx.accounts.pending_change;
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(now >= pending.eta, SportPredictError::TimelockNotElapsed);
+        pending
+            .change
+            .apply(&mut ctx.accounts.platform, &mut ctx.accounts.roles)?;
+
+        emit!(ChangeExecutedEvent {
+            id: pending.id,
+            change: pending.change.clone(),
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    // Drop a scheduled change before it is executed
+    pub fn cancel_change(ctx: Context<CancelChange>) -> Result<()> {
+        emit!(ChangeCancelledEvent {
+            id: ctx.accounts.pending_change.id,
+            by: ctx.accounts.authority.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Withdraw collected fees to the authority. Only fees that have accrued in the ledger can be taken.
+    pub fn withdraw_platform_fees(ctx: Context<WithdrawPlatformFees>, amount: u64) -> Result<()> {
+        ctx.accounts
+            .platform
+            .require_not_paused(PAUSE_WITHDRAWALS)?;
+        let platform = &ctx.accounts.platform;
+        let fee_ledger = &mut ctx.accounts.fee_ledger;
+
+        require!(
+            amount <= fee_ledger.accrued_fees,
+            SportPredictError::InsufficientAccruedFees
+        );
+
+        let platform_seeds: &[&[u8]] = &[b"platform", &[platform.bump]];
+        let signer_seeds = &[platform_seeds];
+        let transfer_ctx = CpiContext::new_with_signer(
             ctx.accounts.token_program.to_account_info(),
             Transfer {
-                from: ctx.accounts.user_token_account.to_account_info(),
-                to: ctx.accounts.platform_vault.to_account_info(),
-                authority: ctx.accounts.authority.to_account_info(),
+                from: ctx.accounts.platform_vault.to_account_info(),
+                to: ctx.accounts.authority_token_account.to_account_info(),
+                authority: platform.to_account_info(),
             },
+            signer_seeds,
         );
-
         token::transfer(transfer_ctx, amount)?;
 
-        // Update user balance
-        user.balance += amount;
-        user.total_volume += amount;
-        platform.total_volume += amount;
+        fee_ledger.accrued_fees = math::sub(fee_ledger.accrued_fees, amount)?;
+        fee_ledger.total_withdrawn = math::add(fee_ledger.total_withdrawn, amount)?;
 
-        // Emit deposit event
-        emit!(DepositEvent {
-            user: user.key(),
+        emit!(PlatformFeesWithdrawnEvent {
+            authority: ctx.accounts.authority.key(),
             amount,
+            remaining: fee_ledger.accrued_fees,
             timestamp: Clock::get()?.unix_timestamp,
         });
 
         Ok(())
     }
 
-    // Place bet
-    pub fn place_bet(
-        ctx: Context<PlaceBet>,
-        match_id: String,
-        prediction: String,
-        amount: u64,
-        odds: f64,
-    ) -> Result<()> {
-        let user = &mut ctx.accounts.user;
-        let bet = &mut ctx.accounts.bet;
-        let platform = &mut ctx.accounts.platform;
-
-        require!(user.balance >= amount, SportPredictError::InsufficientBalance);
-        require!(user.is_active, SportPredictError::UserInactive);
-
-        // Create bet account
-        bet.user = user.key();
-        bet.match_id = match_id;
-        bet.prediction = prediction;
-        bet.amount = amount;
-        bet.odds = odds;
-        bet.potential_winnings = (amount as f64 * odds) as u64;
-        bet.status = BetStatus::Pending;
-        bet.bump = *ctx.bumps.get("bet").unwrap();
-        bet.created_at = Clock::get()?.unix_timestamp;
+    // Halt or resume groups of instructions. Pass PAUSE_ALL to stop everything, 0 to resume.
+    pub fn set_pause_flags(ctx: Context<UpdatePlatform>, pause_flags: u8) -> Result<()> {
+        require!(
+            pause_flags & !PAUSE_ALL == 0,
+            SportPredictError::InvalidPauseFlags
+        );
 
-        // Deduct amount from user balance
-        user.balance -= amount;
-        user.total_bets += 1;
-        platform.total_bets += 1;
+        let platform = &mut ctx.accounts.platform;
+        let previous = platform.pause_flags;
+        platform.pause_flags = pause_flags;
 
-        // Emit bet placed event
-        emit!(BetPlacedEvent {
-            user: user.key(),
-            bet: bet.key(),
-            match_id: bet.match_id.clone(),
-            amount,
-            prediction: bet.prediction.clone(),
+        emit!(PauseFlagsUpdatedEvent {
+            previous,
+            pause_flags,
+            by: ctx.accounts.authority.key(),
             timestamp: Clock::get()?.unix_timestamp,
         });
 
         Ok(())
     }
 
-    // Settle bet
-    pub fn settle_bet(ctx: Context<SettleBet>, result: BetResult) -> Result<()> {
-        let bet = &mut ctx.accounts.bet;
-        let user = &mut ctx.accounts.user;
+    // Deposit liquidity into the house pool. LP shares are minted at the pool's value net of open
+    // exposure, so bettor losses and house edge accrue to existing shares and winning payouts are drawn from them.
+    pub fn lp_deposit(ctx: Context<LpDeposit>, amount: u64) -> Result<()> {
+        ctx.accounts.platform.require_not_paused(PAUSE_DEPOSITS)?;
         let platform = &mut ctx.accounts.platform;
+        let position = &mut ctx.accounts.lp_position;
 
-        require!(bet.status == BetStatus::Pending, SportPredictError::BetAlreadySettled);
+        let pool_value = platform.lp_pool_value();
+        require!(
+            platform.total_lp_shares == 0 || pool_value > 0,
+            SportPredictError::HousePoolInsolvent
+        );
+        let shares = if platform.total_lp_shares == 0 {
+            amount
+        } else {
+            math::mul_div_floor(amount, platform.total_lp_shares, pool_value)?
+        };
+        require!(shares > 0, SportPredictError::InvalidLpAmount);
 
-        bet.result = result;
-        bet.status = BetStatus::Settled;
-        bet.settled_at = Clock::get()?.unix_timestamp;
+        let transfer_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.provider_token_account.to_account_info(),
+                to: ctx.accounts.platform_vault.to_account_info(),
+                authority: ctx.accounts.provider.to_account_info(),
+            },
+        );
+        token::transfer(transfer_ctx, amount)?;
 
-        match result {
-            BetResult::Win => {
-                let winnings = bet.potential_winnings;
-                user.balance += winnings;
-                user.total_wins += 1;
-                bet.winnings = winnings;
+        if position.provider == Pubkey::default() {
+            position.provider = ctx.accounts.provider.key();
+            position.bump = *ctx.bumps.get("lp_position").unwrap();
+        }
+        position.shares = math::add(position.shares, shares)?;
+        position.total_deposited = math::add(position.total_deposited, amount)?;
+        position.last_deposit = Clock::get()?.unix_timestamp;
 
-                // Emit win event
-                emit!(BetWonEvent {
-                    user: user.key(),
-                    bet: bet.key(),
-                    winnings,
-                    timestamp: Clock::get()?.unix_timestamp,
-                });
-            }
-            BetResult::Loss => {
-                bet.winnings = 0;
-                // Emit loss event
-                emit!(BetLostEvent {
-                    user: user.key(),
-                    bet: bet.key(),
-                    timestamp: Clock::get()?.unix_timestamp,
-                });
-            }
-            BetResult::Draw => {
-                // Return original bet amount
-                user.balance += bet.amount;
-                bet.winnings = bet.amount;
+        platform.credit_house(amount)?;
+        platform.total_lp_shares = math::add(platform.total_lp_shares, shares)?;
 
-                // Emit draw event
-                emit!(BetDrawEvent {
-                    user: user.key(),
-                    bet: bet.key(),
-                    refund_amount: bet.amount,
-                    timestamp: Clock::get()?.unix_timestamp,
-                });
-            }
-        }
+        emit!(LpDepositEvent {
+            provider: position.provider,
+            amount,
+            shares,
+            house_reserve: platform.house_reserve,
+            timestamp: position.last_deposit,
+        });
 
         Ok(())
     }
 
-    // Withdraw funds
-    pub fn withdraw(ctx: Context<Withdraw>, amount: u64) -> Result<()> {
-        let user = &mut ctx.accounts.user;
+    // Redeem LP shares for their share of the house pool. Liquidity locked by open exposure stays in the pool.
+    pub fn lp_withdraw(ctx: Context<LpWithdraw>, shares: u64) -> Result<()> {
+        ctx.accounts
+            .platform
+            .require_not_paused(PAUSE_WITHDRAWALS)?;
         let platform = &mut ctx.accounts.platform;
+        let position = &mut ctx.accounts.lp_position;
 
-        require!(user.balance >= amount, SportPredictError::InsufficientBalance);
-        require!(user.is_active, SportPredictError::UserInactive);
+        require!(
+            shares > 0 && position.shares >= shares,
+            SportPredictError::InsufficientShares
+        );
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            now >= math::add_seconds(position.last_deposit, MIN_LP_HOLD_PERIOD)?,
+            SportPredictError::LpHoldPeriod
+        );
 
-        // Transfer tokens from platform vault to user
-        let transfer_ctx = CpiContext::new(
+        // Redeemed at the share price net of open exposure, rounded down in the pool's favour
+        let amount =
+            math::mul_div_floor(shares, platform.lp_pool_value(), platform.total_lp_shares)?;
+        require!(amount > 0, SportPredictError::LiquidityLocked);
+
+        let platform_seeds: &[&[u8]] = &[b"platform", &[platform.bump]];
+        let signer_seeds = &[platform_seeds];
+        let transfer_ctx = CpiContext::new_with_signer(
             ctx.accounts.token_program.to_account_info(),
             Transfer {
                 from: ctx.accounts.platform_vault.to_account_info(),
-                to: ctx.accounts.user_token_account.to_account_info(),
-                authority: ctx.accounts.platform.to_account_info(),
+                to: ctx.accounts.provider_token_account.to_account_info(),
+                authority: platform.to_account_info(),
             },
+            signer_seeds,
         );
-
         token::transfer(transfer_ctx, amount)?;
 
-        // Update user balance
-        user.balance -= amount;
-        platform.total_volume -= amount;
+        position.shares = math::sub(position.shares, shares)?;
+        platform.debit_house(amount)?;
+        platform.total_lp_shares = math::sub(platform.total_lp_shares, shares)?;
 
-        // Emit withdrawal event
-        emit!(WithdrawEvent {
-            user: user.key(),
+        emit!(LpWithdrawEvent {
+            provider: position.provider,
             amount,
-            timestamp: Clock::get()?.unix_timestamp,
+            shares,
+            house_reserve: platform.house_reserve,
+            timestamp: now,
         });
 
         Ok(())
     }
 
-    // Create ETF
-    pub fn create_etf(
-        ctx: Context<CreateETF>,
-        name: String,
-        description: String,
-        strategy: String,
-        risk_level: u8,
-        min_investment: u64,
-        max_investment: u64,
-        annual_return: f64,
-        management_fee: f64,
-        performance_fee: f64,
-    ) -> Result<()> {
-        let etf = &mut ctx.accounts.etf;
-        let manager = &mut ctx.accounts.manager;
+    // Create user account. The username is claimed through its own PDA so no two users can share it.
+    pub fn create_user(ctx: Context<CreateUser>, username: String) -> Result<()> {
+        User::validate_username(&username)?;
 
-        etf.manager = manager.key();
-        etf.name = name;
-        etf.description = description;
-        etf.strategy = strategy;
-        etf.risk_level = risk_level;
-        etf.min_investment = min_investment;
-        etf.max_investment = max_investment;
-        etf.annual_return = annual_return;
-        etf.management_fee = management_fee;
-        etf.performance_fee = performance_fee;
-        etf.status = ETFStatus::Active;
-        etf.total_value = 0;
-        etf.total_investors = 0;
-        etf.current_return = 0.0;
-        etf.bump = *ctx.bumps.get("etf").unwrap();
-        etf.created_at = Clock::get()?.unix_timestamp;
+        let user = &mut ctx.accounts.user;
+        let platform = &mut ctx.accounts.platform;
+
+        let claim = &mut ctx.accounts.username_claim;
+        claim.user = user.key();
+        claim.bump = *ctx.bumps.get("username_claim").unwrap();
+
+        user.authority = ctx.accounts.authority.key();
+        user.username = username;
+        user.bump = *ctx.bumps.get("user").unwrap();
+        user.balance = 0;
+        user.total_bets = 0;
+        user.total_wins = 0;
+        user.total_volume = 0;
+        user.is_active = true;
+        user.bet_nonce = 0;
+        user.etf_nonce = 0;
+        user.limits = GamblingLimits::default();
+        user.excluded_until = 0;
+
+        platform.total_users += 1;
 
         Ok(())
     }
 
-    // Invest in ETF
-    pub fn invest_in_etf(ctx: Context<InvestInETF>, amount: u64) -> Result<()> {
+    // Set one of the user's own deposit, wager or loss limits for a window. Zero removes the limit.
+    // Lowering a limit applies immediately; raising or removing one waits out the cooling-off period.
+    pub fn set_gambling_limit(
+        ctx: Context<UpdateUserSettings>,
+        kind: LimitKind,
+        window: LimitWindow,
+        amount: u64,
+    ) -> Result<()> {
         let user = &mut ctx.accounts.user;
-        let etf = &mut ctx.accounts.etf;
-        let investment = &mut ctx.accounts.investment;
+        let now = Clock::get()?.unix_timestamp;
+        let effective_at = user
+            .limits
+            .window_mut(window)
+            .limit_mut(kind)
+            .update(amount, now)?;
 
-        require!(user.balance >= amount, SportPredictError::InsufficientBalance);
-        require!(user.is_active, SportPredictError::UserInactive);
-        require!(etf.status == ETFStatus::Active, SportPredictError::ETFInactive);
-        require!(amount >= etf.min_investment, SportPredictError::InvestmentTooSmall);
-        require!(amount <= etf.max_investment, SportPredictError::InvestmentTooLarge);
+        emit!(GamblingLimitUpdatedEvent {
+            user: user.key(),
+            kind,
+            window,
+            amount,
+            effective_at,
+            timestamp: now,
+        });
 
-        // Create investment account
-        investment.user = user.key();
-        investment.etf = etf.key();
-        investment.amount = amount;
-        investment.shares = amount; // 1:1 ratio for simplicity
-        investment.status = InvestmentStatus::Active;
-        investment.bump = *ctx.bumps.get("investment").unwrap();
-        investment.created_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
 
-        // Update user and ETF balances
-        user.balance -= amount;
-        etf.total_value += amount;
-        etf.total_investors += 1;
+    // Lock the caller out of betting and ETF investing until `until`, or permanently when None.
+    // Withdrawals stay open. An exclusion can be extended but never shortened or lifted early.
+    pub fn self_exclude(ctx: Context<UpdateUserSettings>, until: Option<i64>) -> Result<()> {
+        let user = &mut ctx.accounts.user;
+        let now = Clock::get()?.unix_timestamp;
+        let until = until.unwrap_or(i64::MAX);
 
-        // Emit investment event
-        emit!(ETFInvestmentEvent {
+        require!(until > now, SportPredictError::InvalidExclusionPeriod);
+        require!(
+            until >= user.excluded_until,
+            SportPredictError::ExclusionCannotBeShortened
+        );
+        user.excluded_until = until;
+
+        emit!(UserSelfExcludedEvent {
             user: user.key(),
-            etf: etf.key(),
-            amount,
-            shares: investment.shares,
-            timestamp: Clock::get()?.unix_timestamp,
+            excluded_until: until,
+            timestamp: now,
         });
 
         Ok(())
     }
 
-    // Redeem from ETF
-    pub fn redeem_from_etf(ctx: Context<RedeemFromETF>, shares: u64) -> Result<()> {
-        let user = &mut ctx.accounts.user;
-        let etf = &mut ctx.accounts.etf;
-        let investment = &mut ctx.accounts.investment;
+    // Record the result of an off-chain identity check for a wallet, replacing any earlier attestation
+    pub fn issue_attestation(
+        ctx: Context<IssueAttestation>,
+        wallet: Pubkey,
+        kyc_level: u8,
+        expires_at: i64,
+        age_verified: bool,
+        region_codes: Vec<String>,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        require!(expires_at > now, SportPredictError::AttestationExpired);
+        // Without a region the holder could never match a block, so one is always required
+        require!(
+            !region_codes.is_empty(),
+            SportPredictError::MissingRegionCode
+        );
+        let regions = Attestation::parse_regions(&region_codes, MAX_ATTESTED_REGIONS)?;
 
-        require!(investment.shares >= shares, SportPredictError::InsufficientShares);
-        require!(investment.status == InvestmentStatus::Active, SportPredictError::InvestmentInactive);
+        let attestation = &mut ctx.accounts.attestation;
+        attestation.wallet = wallet;
+        attestation.kyc_level = kyc_level;
+        attestation.expires_at = expires_at;
+        attestation.age_verified = age_verified;
+        attestation.regions = regions.clone();
+        attestation.issued_by = ctx.accounts.compliance_officer.key();
+        attestation.issued_at = now;
+        attestation.bump = *ctx.bumps.get("attestation").unwrap();
 
-        let redemption_amount = (shares as f64 * (etf.total_value as f64 / etf.total_investors as f64)) as u64;
+        emit!(AttestationIssuedEvent {
+            wallet,
+            kyc_level,
+            expires_at,
+            age_verified,
+            regions,
+            issued_by: attestation.issued_by,
+            timestamp: now,
+        });
 
-        // Update investment
-        investment.shares -= shares;
-        if investment.shares == 0 {
-            investment.status = InvestmentStatus::Redeemed;
-            etf.total_investors -= 1;
-        }
+        Ok(())
+    }
 
-        // Update user and ETF balances
-        user.balance += redemption_amount;
-        etf.total_value -= redemption_amount;
+    // Set the regions blocked on every market and ETF
+    pub fn set_default_blocked_regions(
+        ctx: Context<SetDefaultBlockedRegions>,
+        region_codes: Vec<String>,
+    ) -> Result<()> {
+        let platform = &mut ctx.accounts.platform;
+        platform.default_blocked_regions =
+            Attestation::parse_regions(&region_codes, MAX_BLOCKED_REGIONS)?;
 
-        // Emit redemption event
-        emit!(ETFRedeemEvent {
-            user: user.key(),
-            etf: etf.key(),
-            shares,
-            redemption_amount,
+        emit!(BlockedRegionsUpdatedEvent {
+            account: platform.key(),
+            blocked_regions: platform.default_blocked_regions.clone(),
+            by: ctx.accounts.compliance_officer.key(),
             timestamp: Clock::get()?.unix_timestamp,
         });
 
         Ok(())
     }
-}
 
-#[derive(Accounts)]
-pub struct Initialize<'info> {
-    #[account(
-        init,
+    // Block users from the given regions from betting on a market, on top of the platform default
+    pub fn set_market_blocked_regions(
+        ctx: Context<SetMarketBlockedRegions>,
+        region_codes: Vec<String>,
+    ) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        market.blocked_regions =
+            Attestation::parse_regions(&region_codes, MAX_EXTRA_BLOCKED_REGIONS)?;
+
+        emit!(BlockedRegionsUpdatedEvent {
+            account: market.key(),
+            blocked_regions: market.blocked_regions.clone(),
+            by: ctx.accounts.compliance_officer.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Block users from the given regions from investing in an ETF, on top of the platform default
+    pub fn set_etf_blocked_regions(
+        ctx: Context<SetETFBlockedRegions>,
+        region_codes: Vec<String>,
+    ) -> Result<()> {
+        let etf = &mut ctx.accounts.etf;
+        etf.blocked_regions = Attestation::parse_regions(&region_codes, MAX_EXTRA_BLOCKED_REGIONS)?;
+
+        emit!(BlockedRegionsUpdatedEvent {
+            account: etf.key(),
+            blocked_regions: etf.blocked_regions.clone(),
+            by: ctx.accounts.compliance_officer.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Withdraw a wallet's attestation, blocking it from regulated instructions
+    pub fn revoke_attestation(ctx: Context<RevokeAttestation>) -> Result<()> {
+        emit!(AttestationRevokedEvent {
+            wallet: ctx.accounts.attestation.wallet,
+            revoked_by: ctx.accounts.compliance_officer.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Change a user's name, releasing the old claim so someone else can take it
+    pub fn rename_user(ctx: Context<RenameUser>, new_username: String) -> Result<()> {
+        User::validate_username(&new_username)?;
+
+        let user = &mut ctx.accounts.user;
+        let claim = &mut ctx.accounts.new_username_claim;
+        claim.user = user.key();
+        claim.bump = *ctx.bumps.get("new_username_claim").unwrap();
+
+        let old_username = std::mem::replace(&mut user.username, new_username);
+
+        emit!(UserRenamedEvent {
+            user: user.key(),
+            old_username,
+            new_username: user.username.clone(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Deposit funds
+    pub fn deposit(ctx: Context<Deposit>, amount: u64) -> Result<()> {
+        ctx.accounts.platform.require_not_paused(PAUSE_DEPOSITS)?;
+        ctx.accounts.attestation.require_valid(
+            ctx.accounts.platform.min_kyc_level,
+            Clock::get()?.unix_timestamp,
+        )?;
+        let user = &mut ctx.accounts.user;
+        let platform = &mut ctx.accounts.platform;
+
+        user.record_deposit(amount, Clock::get()?.unix_timestamp)?;
+
+        // Transfer tokens from user to platform vault
+        let transfer_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.user_token_account.to_account_info(),
+                to: ctx.accounts.platform_vault.to_account_info(),
+                authority: ctx.accounts.authority.to_account_info(),
+            },
+        );
+
+        token::transfer(transfer_ctx, amount)?;
+
+        let fee = math::fee(amount, platform.deposit_fee_bps)?;
+        ctx.accounts.fee_ledger.accrue(fee)?;
+
+        // Update user balance
+        user.balance = math::add(user.balance, math::sub(amount, fee)?)?;
+        user.total_volume = math::add(user.total_volume, amount)?;
+        platform.total_volume = math::add(platform.total_volume, amount)?;
+
+        // Emit deposit event
+        emit!(DepositEvent {
+            user: user.key(),
+            amount,
+            fee,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Create match
+    pub fn create_match(
+        ctx: Context<CreateMatch>,
+        match_id: String,
+        home_team: String,
+        away_team: String,
+        league: String,
+        sport: String,
+        start_time: i64,
+    ) -> Result<()> {
+        require!(
+            match_id.len() <= MAX_MATCH_ID_LEN,
+            SportPredictError::MatchIdTooLong
+        );
+        require!(
+            home_team.len() <= MAX_NAME_LEN
+                && away_team.len() <= MAX_NAME_LEN
+                && league.len() <= MAX_NAME_LEN
+                && sport.len() <= MAX_NAME_LEN,
+            SportPredictError::NameTooLong
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        require!(start_time > now, SportPredictError::InvalidMatchTime);
+
+        let match_account = &mut ctx.accounts.match_account;
+        match_account.match_id = match_id;
+        match_account.home_team = home_team;
+        match_account.away_team = away_team;
+        match_account.league = league;
+        match_account.sport = sport;
+        match_account.start_time = start_time;
+        match_account.end_time = None;
+        match_account.status = MatchStatus::Upcoming;
+        match_account.market_count = 0;
+        match_account.created_by = ctx.accounts.authority.key();
+        match_account.bump = *ctx.bumps.get("match_account").unwrap();
+        match_account.created_at = now;
+
+        emit!(MatchCreatedEvent {
+            match_account: match_account.key(),
+            match_id: match_account.match_id.clone(),
+            start_time,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    // Update match status
+    pub fn update_match_status(ctx: Context<UpdateMatch>, status: MatchStatus) -> Result<()> {
+        let match_account = &mut ctx.accounts.match_account;
+
+        require!(
+            match_account.status.can_transition_to(&status),
+            SportPredictError::InvalidMatchStatusTransition
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        if status == MatchStatus::Finished {
+            match_account.end_time = Some(now);
+        }
+        match_account.status = status.clone();
+
+        emit!(MatchStatusUpdatedEvent {
+            match_account: match_account.key(),
+            status,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    // Cancel a postponed or abandoned match. Pending bets on it are refunded through refund_cancelled_bets.
+    pub fn cancel_match(ctx: Context<UpdateMatch>) -> Result<()> {
+        let match_account = &mut ctx.accounts.match_account;
+
+        require!(
+            match_account.status == MatchStatus::Upcoming
+                || match_account.status == MatchStatus::Live,
+            SportPredictError::InvalidMatchStatusTransition
+        );
+        match_account.status = MatchStatus::Cancelled;
+
+        emit!(MatchStatusUpdatedEvent {
+            match_account: match_account.key(),
+            status: MatchStatus::Cancelled,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Void and refund pending bets on a cancelled match. Permissionless crank; `remaining_accounts`
+    // holds (bet, user) pairs. Bets that are no longer pending are skipped.
+    pub fn refund_cancelled_bets<'info>(
+        ctx: Context<'_, '_, '_, 'info, RefundCancelledBets<'info>>,
+    ) -> Result<()> {
+        ctx.accounts.platform.require_not_paused(PAUSE_SETTLEMENT)?;
+        let match_key = ctx.accounts.match_account.key();
+        require!(
+            ctx.accounts.match_account.status == MatchStatus::Cancelled,
+            SportPredictError::MatchNotCancelled
+        );
+        require!(
+            ctx.remaining_accounts
+                .chunks_exact(2)
+                .remainder()
+                .is_empty(),
+            SportPredictError::InvalidRemainingAccounts
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        let mut refunded: u32 = 0;
+        let mut skipped: u32 = 0;
+        let mut total_refunded: u64 = 0;
+
+        for pair in ctx.remaining_accounts.chunks(2) {
+            let mut bet = Account::<Bet>::try_from(&pair[0])?;
+            let mut user = Account::<User>::try_from(&pair[1])?;
+            bet.verify_address(pair[0].key, ctx.program_id)?;
+            user.verify_address(pair[1].key, ctx.program_id)?;
+            require_keys_eq!(
+                bet.match_account,
+                match_key,
+                SportPredictError::MatchMismatch
+            );
+            require_keys_eq!(user.key(), bet.user, SportPredictError::InvalidUserAccount);
+
+            if bet.status != BetStatus::Pending {
+                skipped += 1;
+                continue;
+            }
+
+            bet.status = BetStatus::Cancelled;
+            bet.result = Some(BetResult::Void);
+            bet.winnings = Some(bet.amount);
+            bet.settled_at = Some(now);
+            user.balance = math::add(user.balance, bet.amount)?;
+            user.record_return(bet.amount, now)?;
+
+            emit!(BetVoidedEvent {
+                user: user.key(),
+                bet: bet.key(),
+                refund_amount: bet.amount,
+                timestamp: now,
+            });
+
+            refunded += 1;
+            total_refunded = math::add(total_refunded, bet.amount)?;
+            bet.exit(ctx.program_id)?;
+            user.exit(ctx.program_id)?;
+        }
+
+        emit!(MatchBetsRefundedEvent {
+            match_account: match_key,
+            refunded,
+            skipped,
+            total_refunded,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    // Void a market on a cancelled match and release its exposure. Permissionless.
+    pub fn release_market_exposure(ctx: Context<ReleaseMarketExposure>) -> Result<()> {
+        ctx.accounts.platform.require_not_paused(PAUSE_SETTLEMENT)?;
+        let market = &mut ctx.accounts.market;
+        let platform = &mut ctx.accounts.platform;
+
+        require!(
+            ctx.accounts.match_account.status == MatchStatus::Cancelled,
+            SportPredictError::MatchNotCancelled
+        );
+        require!(
+            market.status != MarketStatus::Voided,
+            SportPredictError::MarketAlreadyResolved
+        );
+
+        platform.update_exposure(market.exposure, 0)?;
+        market.exposure = 0;
+        market.status = MarketStatus::Voided;
+
+        Ok(())
+    }
+
+    // Create market
+    pub fn create_market(
+        ctx: Context<CreateMarket>,
+        market_type: MarketType,
+        outcomes: Vec<Outcome>,
+    ) -> Result<()> {
+        let match_account = &mut ctx.accounts.match_account;
+        let market = &mut ctx.accounts.market;
+
+        require!(
+            match_account.status == MatchStatus::Upcoming,
+            SportPredictError::BettingClosed
+        );
+        market_type.validate_outcomes(&outcomes)?;
+
+        market.match_account = match_account.key();
+        market.index = match_account.market_count;
+        market.market_type = market_type;
+        // Outcomes open without a price; betting starts once the odds setter publishes odds
+        market.outcomes = outcomes
+            .into_iter()
+            .map(|outcome| MarketOutcome {
+                outcome,
+                odds: 0,
+                stake: 0,
+                liability: 0,
+            })
+            .collect();
+        market.ballots = Vec::new();
+        market.total_stake = 0;
+        market.exposure = 0;
+        market.blocked_regions = Vec::new();
+        market.odds_version = 0;
+        market.odds_updated_at = 0;
+        market.status = MarketStatus::Open;
+        market.proposed_outcome = None;
+        market.proposed_at = None;
+        market.winning_outcome = None;
+        market.resolved_at = None;
+        market.bump = *ctx.bumps.get("market").unwrap();
+        market.created_at = Clock::get()?.unix_timestamp;
+
+        match_account.market_count = match_account
+            .market_count
+            .checked_add(1)
+            .ok_or(SportPredictError::TooManyMarkets)?;
+
+        emit!(MarketCreatedEvent {
+            match_account: match_account.key(),
+            market: market.key(),
+            market_type: market.market_type.clone(),
+            outcome_count: market.outcomes.len() as u8,
+            timestamp: market.created_at,
+        });
+
+        Ok(())
+    }
+
+    // Publish odds for every outcome of a market
+    pub fn set_odds(ctx: Context<SetOdds>, odds: Vec<u32>) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+
+        // In-play prices are still published so cash-out quotes track the match
+        require!(
+            ctx.accounts.match_account.status == MatchStatus::Upcoming
+                || ctx.accounts.match_account.status == MatchStatus::Live,
+            SportPredictError::BettingClosed
+        );
+        require!(
+            market.status == MarketStatus::Open,
+            SportPredictError::MarketAlreadyResolved
+        );
+        require!(
+            odds.len() == market.outcomes.len(),
+            SportPredictError::InvalidOdds
+        );
+        for price in odds.iter() {
+            math::validate_odds(*price)?;
+        }
+
+        for (outcome, price) in market.outcomes.iter_mut().zip(odds.iter()) {
+            outcome.odds = *price;
+        }
+        market.odds_version = math::add(market.odds_version, 1)?;
+        market.odds_updated_at = Clock::get()?.unix_timestamp;
+
+        emit!(OddsUpdatedEvent {
+            market: market.key(),
+            odds,
+            version: market.odds_version,
+            timestamp: market.odds_updated_at,
+        });
+
+        Ok(())
+    }
+
+    // Place bet at the market's published odds. Fails if the price moved below `min_odds`
+    // or, when given, if the odds version no longer matches what the client quoted.
+    // `nonce` must equal the user's `bet_nonce`; it seeds the bet address.
+    pub fn place_bet(
+        ctx: Context<PlaceBet>,
+        nonce: u64,
+        outcome_index: u8,
+        amount: u64,
+        min_odds: u32,
+        expected_odds_version: Option<u64>,
+    ) -> Result<()> {
+        ctx.accounts.platform.require_not_paused(PAUSE_BETTING)?;
+        ctx.accounts.attestation.require_valid(
+            ctx.accounts.platform.min_kyc_level,
+            Clock::get()?.unix_timestamp,
+        )?;
+        let user = &mut ctx.accounts.user;
+        let bet = &mut ctx.accounts.bet;
+        let platform = &mut ctx.accounts.platform;
+        let match_account = &ctx.accounts.match_account;
+        let market = &mut ctx.accounts.market;
+
+        require!(nonce == user.bet_nonce, SportPredictError::InvalidNonce);
+        require!(
+            user.balance >= amount,
+            SportPredictError::InsufficientBalance
+        );
+        require!(user.is_active, SportPredictError::UserInactive);
+        require!(
+            !user.is_excluded(Clock::get()?.unix_timestamp),
+            SportPredictError::SelfExcluded
+        );
+        require!(
+            match_account.status == MatchStatus::Upcoming
+                && Clock::get()?.unix_timestamp < match_account.start_time
+                && market.status == MarketStatus::Open,
+            SportPredictError::BettingClosed
+        );
+        let odds = market
+            .outcomes
+            .get(outcome_index as usize)
+            .ok_or(SportPredictError::InvalidOutcome)?
+            .odds;
+        require!(odds != 0, SportPredictError::OddsNotPublished);
+        if let Some(version) = expected_odds_version {
+            require!(
+                market.odds_version == version,
+                SportPredictError::OddsChanged
+            );
+        }
+        require!(
+            !ctx.accounts
+                .attestation
+                .is_blocked(platform, &market.blocked_regions),
+            SportPredictError::RegionRestricted
+        );
+        require!(odds >= min_odds, SportPredictError::OddsBelowMinimum);
+
+        // Create bet account, locking in the current price
+        bet.user = user.key();
+        bet.match_account = match_account.key();
+        bet.market = market.key();
+        bet.outcome_index = outcome_index;
+        bet.amount = amount;
+        bet.odds = odds;
+        bet.potential_winnings = math::payout(amount, odds as u64)?;
+        bet.cashed_out = 0;
+        bet.status = BetStatus::Pending;
+        bet.nonce = nonce;
+        bet.bump = *ctx.bumps.get("bet").unwrap();
+        bet.created_at = Clock::get()?.unix_timestamp;
+
+        // Book the bet against the market and make sure the house can cover it
+        let old_exposure = market.exposure;
+        market.add_position(outcome_index, amount, bet.potential_winnings)?;
+        platform.update_exposure(old_exposure, market.exposure)?;
+        if market.exposure > old_exposure {
+            require!(
+                market.exposure <= platform.max_market_exposure,
+                SportPredictError::MarketExposureExceeded
+            );
+            platform.check_exposure()?;
+        }
+
+        // Deduct amount from user balance
+        user.record_wager(amount, bet.created_at)?;
+        user.balance = math::sub(user.balance, amount)?;
+        user.total_bets += 1;
+        user.bet_nonce = math::add(user.bet_nonce, 1)?;
+        platform.total_bets += 1;
+
+        // Emit bet placed event
+        emit!(BetPlacedEvent {
+            user: user.key(),
+            bet: bet.key(),
+            match_account: bet.match_account,
+            match_id: match_account.match_id.clone(),
+            amount,
+            market: bet.market,
+            outcome_index,
+            odds,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Place a multi-leg bet. `remaining_accounts` holds a (match, market) pair per leg, in the same
+    // order as `outcome_indices`. Each leg locks the market's current odds.
+    // Parlays share the user's `bet_nonce` sequence with single bets.
+    pub fn place_parlay<'info>(
+        ctx: Context<'_, '_, '_, 'info, PlaceParlay<'info>>,
+        nonce: u64,
+        outcome_indices: Vec<u8>,
+        amount: u64,
+        min_combined_odds: u64,
+    ) -> Result<()> {
+        ctx.accounts.platform.require_not_paused(PAUSE_BETTING)?;
+        ctx.accounts.attestation.require_valid(
+            ctx.accounts.platform.min_kyc_level,
+            Clock::get()?.unix_timestamp,
+        )?;
+        let leg_count = outcome_indices.len();
+        require!(
+            (2..=MAX_PARLAY_LEGS).contains(&leg_count),
+            SportPredictError::InvalidParlayLegs
+        );
+        require!(
+            ctx.remaining_accounts.len() == leg_count * 2,
+            SportPredictError::InvalidRemainingAccounts
+        );
+
+        let user = &mut ctx.accounts.user;
+        let parlay = &mut ctx.accounts.parlay;
+        let platform = &mut ctx.accounts.platform;
+
+        require!(nonce == user.bet_nonce, SportPredictError::InvalidNonce);
+        require!(
+            user.balance >= amount,
+            SportPredictError::InsufficientBalance
+        );
+        require!(user.is_active, SportPredictError::UserInactive);
+        require!(
+            !user.is_excluded(Clock::get()?.unix_timestamp),
+            SportPredictError::SelfExcluded
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        let mut legs: Vec<ParlayLeg> = Vec::with_capacity(leg_count);
+        let mut combined_odds = math::ODDS_SCALE;
+
+        for (pair, outcome_index) in ctx.remaining_accounts.chunks(2).zip(outcome_indices.iter()) {
+            let match_account = Account::<Match>::try_from(&pair[0])?;
+            let market = Account::<Market>::try_from(&pair[1])?;
+            market.verify_address(pair[1].key, ctx.program_id)?;
+            require_keys_eq!(
+                market.match_account,
+                *pair[0].key,
+                SportPredictError::MatchMismatch
+            );
+            require!(
+                match_account.status == MatchStatus::Upcoming
+                    && now < match_account.start_time
+                    && market.status == MarketStatus::Open,
+                SportPredictError::BettingClosed
+            );
+            require!(
+                !ctx.accounts
+                    .attestation
+                    .is_blocked(platform, &market.blocked_regions),
+                SportPredictError::RegionRestricted
+            );
+            // Legs on the same match are correlated, so each match may appear only once
+            require!(
+                !legs.iter().any(|leg| leg.match_account == *pair[0].key),
+                SportPredictError::DuplicateParlayMatch
+            );
+
+            let odds = market
+                .outcomes
+                .get(*outcome_index as usize)
+                .ok_or(SportPredictError::InvalidOutcome)?
+                .odds;
+            require!(odds != 0, SportPredictError::OddsNotPublished);
+
+            combined_odds = math::combine_odds(combined_odds, odds)?;
+            legs.push(ParlayLeg {
+                match_account: *pair[0].key,
+                market: *pair[1].key,
+                outcome_index: *outcome_index,
+                odds,
+                status: LegStatus::Pending,
+            });
+        }
+        require!(
+            combined_odds >= min_combined_odds,
+            SportPredictError::OddsBelowMinimum
+        );
+
+        parlay.user = user.key();
+        parlay.legs = legs;
+        parlay.amount = amount;
+        parlay.combined_odds = combined_odds;
+        parlay.potential_winnings = math::payout(amount, combined_odds)?;
+        parlay.status = BetStatus::Pending;
+        parlay.nonce = nonce;
+        parlay.bump = *ctx.bumps.get("parlay").unwrap();
+        parlay.created_at = now;
+
+        // Parlays are tracked against the platform-wide limit only
+        platform.update_exposure(0, parlay.exposure()?)?;
+        platform.check_exposure()?;
+
+        user.record_wager(amount, now)?;
+        user.balance = math::sub(user.balance, amount)?;
+        user.total_bets += 1;
+        user.bet_nonce = math::add(user.bet_nonce, 1)?;
+        platform.total_bets += 1;
+
+        emit!(ParlayPlacedEvent {
+            user: user.key(),
+            parlay: parlay.key(),
+            legs: leg_count as u8,
+            amount,
+            combined_odds,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    // Update a parlay's legs from their markets and settle it once decided. Permissionless;
+    // `remaining_accounts` holds (match, market) pairs for the legs being updated.
+    // Any losing leg loses the parlay; voided legs drop out and the combined odds are recomputed.
+    pub fn settle_parlay<'info>(
+        ctx: Context<'_, '_, '_, 'info, SettleParlay<'info>>,
+    ) -> Result<()> {
+        ctx.accounts.platform.require_not_paused(PAUSE_SETTLEMENT)?;
+        let parlay = &mut ctx.accounts.parlay;
+        let user = &mut ctx.accounts.user;
+        let platform = &mut ctx.accounts.platform;
+
+        require!(
+            parlay.status == BetStatus::Pending,
+            SportPredictError::BetAlreadySettled
+        );
+        require!(
+            ctx.remaining_accounts
+                .chunks_exact(2)
+                .remainder()
+                .is_empty(),
+            SportPredictError::InvalidRemainingAccounts
+        );
+
+        for pair in ctx.remaining_accounts.chunks(2) {
+            let match_account = Account::<Match>::try_from(&pair[0])?;
+            let market = Account::<Market>::try_from(&pair[1])?;
+            market.verify_address(pair[1].key, ctx.program_id)?;
+            require_keys_eq!(
+                market.match_account,
+                *pair[0].key,
+                SportPredictError::MatchMismatch
+            );
+
+            let leg = parlay
+                .legs
+                .iter_mut()
+                .find(|leg| leg.market == *pair[1].key)
+                .ok_or(SportPredictError::MarketMismatch)?;
+            if leg.status != LegStatus::Pending {
+                continue;
+            }
+
+            if match_account.status == MatchStatus::Cancelled {
+                leg.status = LegStatus::Void;
+            } else if let Some(winning_outcome) = market.winning_outcome {
+                leg.status = if winning_outcome == leg.outcome_index {
+                    LegStatus::Won
+                } else {
+                    LegStatus::Lost
+                };
+            }
+        }
+
+        let old_exposure = parlay.exposure()?;
+        let mut combined_odds = math::ODDS_SCALE;
+        for leg in parlay
+            .legs
+            .iter()
+            .filter(|leg| leg.status != LegStatus::Void)
+        {
+            combined_odds = math::combine_odds(combined_odds, leg.odds)?;
+        }
+        parlay.combined_odds = combined_odds;
+        parlay.potential_winnings = math::payout(parlay.amount, combined_odds)?;
+
+        let lost = parlay.legs.iter().any(|leg| leg.status == LegStatus::Lost);
+        let decided = lost
+            || parlay
+                .legs
+                .iter()
+                .all(|leg| leg.status != LegStatus::Pending);
+        if !decided {
+            platform.update_exposure(old_exposure, parlay.exposure()?)?;
+            return Ok(());
+        }
+
+        platform.update_exposure(old_exposure, 0)?;
+        let mut fee = 0;
+        let (result, winnings) = if lost {
+            platform.credit_house(parlay.amount)?;
+            (BetResult::Loss, 0)
+        } else if parlay.legs.iter().all(|leg| leg.status == LegStatus::Void) {
+            (BetResult::Void, parlay.amount)
+        } else {
+            let profit = parlay.exposure()?;
+            platform.debit_house(profit)?;
+            fee = math::fee(profit, platform.winnings_fee_bps)?;
+            ctx.accounts.fee_ledger.accrue(fee)?;
+            user.total_wins += 1;
+            (BetResult::Win, math::sub(parlay.potential_winnings, fee)?)
+        };
+
+        let now = Clock::get()?.unix_timestamp;
+        parlay.status = BetStatus::Settled;
+        parlay.result = Some(result.clone());
+        parlay.winnings = Some(winnings);
+        parlay.settled_at = Some(now);
+        user.balance = math::add(user.balance, winnings)?;
+        user.record_return(winnings, now)?;
+
+        emit!(ParlaySettledEvent {
+            user: user.key(),
+            parlay: parlay.key(),
+            result,
+            winnings,
+            fee,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    // Cancel a pending bet before the platform's cutoff ahead of kickoff. The stake, minus the
+    // cancellation fee, is returned to the bettor's balance.
+    pub fn cancel_bet(ctx: Context<CancelBet>) -> Result<()> {
+        ctx.accounts.platform.require_not_paused(PAUSE_BETTING)?;
+        let bet = &mut ctx.accounts.bet;
+        let user = &mut ctx.accounts.user;
+        let platform = &mut ctx.accounts.platform;
+        let match_account = &ctx.accounts.match_account;
+        let market = &mut ctx.accounts.market;
+
+        require!(
+            bet.status == BetStatus::Pending,
+            SportPredictError::BetAlreadySettled
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        let cutoff = match_account
+            .start_time
+            .checked_sub(platform.bet_cancel_cutoff)
+            .ok_or(SportPredictError::MathOverflow)?;
+        require!(
+            match_account.status == MatchStatus::Upcoming && now < cutoff,
+            SportPredictError::CancellationClosed
+        );
+
+        let fee = math::fee(bet.amount, platform.cancellation_fee_bps)?;
+        let refund_amount = math::sub(bet.amount, fee)?;
+
+        let old_exposure = market.exposure;
+        market.remove_position(bet.outcome_index, bet.amount, bet.potential_winnings)?;
+        platform.update_exposure(old_exposure, market.exposure)?;
+        platform.credit_house(fee)?;
+
+        bet.status = BetStatus::Cancelled;
+        bet.winnings = Some(refund_amount);
+        bet.settled_at = Some(now);
+        user.balance = math::add(user.balance, refund_amount)?;
+        user.record_return(refund_amount, now)?;
+
+        emit!(BetCancelledEvent {
+            user: user.key(),
+            bet: bet.key(),
+            refund_amount,
+            fee,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    // Close all or part of a pending bet early. The quote is the stake's value at the market's
+    // current odds (stake * locked odds / current odds) less the platform's cash-out margin.
+    pub fn cash_out(ctx: Context<CashOut>, fraction_bps: u16, min_amount: u64) -> Result<()> {
+        ctx.accounts.platform.require_not_paused(PAUSE_BETTING)?;
+        let bet = &mut ctx.accounts.bet;
+        let user = &mut ctx.accounts.user;
+        let market = &mut ctx.accounts.market;
+        let platform = &mut ctx.accounts.platform;
+
+        require!(
+            bet.status == BetStatus::Pending,
+            SportPredictError::BetAlreadySettled
+        );
+        require!(
+            fraction_bps > 0 && fraction_bps as u64 <= math::BPS_SCALE,
+            SportPredictError::InvalidCashOutFraction
+        );
+        require!(
+            market.status == MarketStatus::Open
+                && (ctx.accounts.match_account.status == MatchStatus::Upcoming
+                    || ctx.accounts.match_account.status == MatchStatus::Live),
+            SportPredictError::CashOutUnavailable
+        );
+
+        let current_odds = market.outcomes[bet.outcome_index as usize].odds;
+        require!(current_odds != 0, SportPredictError::OddsNotPublished);
+
+        // In-play odds move with the game; quoting from old ones would pay out on a known swing
+        let now = Clock::get()?.unix_timestamp;
+        if ctx.accounts.match_account.status == MatchStatus::Live {
+            require!(
+                now <= math::add_seconds(market.odds_updated_at, platform.max_odds_age)?,
+                SportPredictError::StaleOdds
+            );
+        }
+
+        let full = fraction_bps as u64 == math::BPS_SCALE;
+        let stake = if full {
+            bet.amount
+        } else {
+            math::mul_div_floor(bet.amount, fraction_bps as u64, math::BPS_SCALE)?
+        };
+        require!(stake > 0, SportPredictError::InvalidCashOutFraction);
+
+        let fair_value = math::mul_div_floor(stake, bet.odds as u64, current_odds as u64)?;
+        let margin = math::fee(fair_value, platform.cash_out_margin_bps)?;
+        let amount = math::sub(fair_value, margin)?;
+        require!(amount >= min_amount, SportPredictError::CashOutBelowMinimum);
+
+        let old_potential_winnings = bet.potential_winnings;
+        bet.amount = math::sub(bet.amount, stake)?;
+        bet.potential_winnings = math::payout(bet.amount, bet.odds as u64)?;
+
+        // The house keeps the cashed-out stake and funds the quote
+        let old_exposure = market.exposure;
+        market.remove_position(
+            bet.outcome_index,
+            stake,
+            math::sub(old_potential_winnings, bet.potential_winnings)?,
+        )?;
+        platform.update_exposure(old_exposure, market.exposure)?;
+        platform.credit_house(stake)?;
+        platform.debit_house(amount)?;
+        bet.cashed_out = math::add(bet.cashed_out, amount)?;
+        if full || bet.amount == 0 {
+            bet.status = BetStatus::CashedOut;
+            bet.winnings = Some(bet.cashed_out);
+            bet.settled_at = Some(now);
+        }
+        user.balance = math::add(user.balance, amount)?;
+        user.record_return(amount, now)?;
+
+        emit!(BetCashedOutEvent {
+            user: user.key(),
+            bet: bet.key(),
+            stake,
+            amount,
+            remaining_stake: bet.amount,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    // Submit an oracle's result for a market. Once `quorum` oracles agree the outcome is proposed
+    // and opens for challenges until the platform's dispute period has elapsed. If a split vote
+    // leaves no outcome able to reach quorum, the market is handed to the arbitrator.
+    pub fn submit_result(ctx: Context<SubmitResult>, outcome_index: u8) -> Result<()> {
+        ctx.accounts.platform.require_not_paused(PAUSE_SETTLEMENT)?;
+        let market = &mut ctx.accounts.market;
+        let vote = &mut ctx.accounts.oracle_vote;
+        let quorum = ctx.accounts.roles.oracle_quorum;
+
+        require!(quorum > 0, SportPredictError::InvalidQuorum);
+        require!(
+            ctx.accounts.match_account.status == MatchStatus::Finished,
+            SportPredictError::MatchNotFinished
+        );
+        require!(
+            market.status == MarketStatus::Open,
+            SportPredictError::MarketAlreadyResolved
+        );
+        require!(
+            (outcome_index as usize) < market.outcomes.len(),
+            SportPredictError::InvalidOutcome
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        vote.market = market.key();
+        vote.oracle = ctx.accounts.oracle.key();
+        vote.outcome_index = outcome_index;
+        vote.bump = *ctx.bumps.get("oracle_vote").unwrap();
+        vote.submitted_at = now;
+
+        // Flag disagreement so the backend can investigate before the quorum settles it
+        let conflicting = market
+            .ballots
+            .iter()
+            .any(|ballot| ballot.outcome_index != outcome_index);
+        if conflicting {
+            emit!(ConflictingVoteEvent {
+                market: market.key(),
+                oracle: vote.oracle,
+                outcome_index,
+                timestamp: now,
+            });
+        }
+
+        require!(
+            market.ballots.len() < MAX_ROLE_GRANTS,
+            SportPredictError::TooManyRoleGrants
+        );
+        market.ballots.push(OracleBallot {
+            oracle: vote.oracle,
+            outcome_index,
+        });
+
+        // Only keys that still hold the oracle role count, so a revoked oracle's vote drops out
+        let (tally, outstanding) = market.tally(&ctx.accounts.roles);
+        let votes = tally[outcome_index as usize] as u8;
+
+        emit!(OracleVoteEvent {
+            market: market.key(),
+            oracle: vote.oracle,
+            outcome_index,
+            votes,
+            timestamp: now,
+        });
+
+        if votes >= quorum {
+            market.status = MarketStatus::Proposed;
+            market.proposed_outcome = Some(outcome_index);
+            market.proposed_at = Some(now);
+
+            emit!(ResultProposedEvent {
+                market: market.key(),
+                outcome_index,
+                votes,
+                dispute_deadline: math::add_seconds(now, ctx.accounts.platform.dispute_period)?,
+                timestamp: now,
+            });
+        } else {
+            // Oracles that have not voted yet could still back the leading outcome; if even that
+            // falls short of quorum, no further vote can settle the market
+            let cast: usize = tally.iter().sum();
+            let leading = tally.iter().copied().max().unwrap_or(0);
+            if leading + outstanding < quorum as usize {
+                market.status = MarketStatus::Deadlocked;
+
+                emit!(QuorumDeadlockedEvent {
+                    market: market.key(),
+                    votes_cast: cast as u8,
+                    quorum,
+                    timestamp: now,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    // Challenge a proposed result by posting the platform's bond from the challenger's balance.
+    // Claims on the market are frozen until the arbitrator rules.
+    pub fn challenge_result(ctx: Context<ChallengeResult>, outcome_index: u8) -> Result<()> {
+        ctx.accounts.platform.require_not_paused(PAUSE_SETTLEMENT)?;
+        let market = &mut ctx.accounts.market;
+        let dispute = &mut ctx.accounts.dispute;
+        let user = &mut ctx.accounts.user;
+        let bond = ctx.accounts.platform.challenge_bond;
+
+        require!(
+            market.status == MarketStatus::Proposed,
+            SportPredictError::MarketNotProposed
+        );
+        let proposed_outcome = market
+            .proposed_outcome
+            .ok_or(SportPredictError::MarketNotProposed)?;
+        let proposed_at = market
+            .proposed_at
+            .ok_or(SportPredictError::MarketNotProposed)?;
+
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            now < math::add_seconds(proposed_at, ctx.accounts.platform.dispute_period)?,
+            SportPredictError::DisputePeriodEnded
+        );
+        require!(
+            (outcome_index as usize) < market.outcomes.len() && outcome_index != proposed_outcome,
+            SportPredictError::InvalidOutcome
+        );
+        require!(user.balance >= bond, SportPredictError::InsufficientBalance);
+
+        user.balance = math::sub(user.balance, bond)?;
+
+        dispute.market = market.key();
+        dispute.challenger = user.authority;
+        dispute.proposed_outcome = proposed_outcome;
+        dispute.challenged_outcome = outcome_index;
+        dispute.bond = bond;
+        dispute.status = DisputeStatus::Open;
+        dispute.bump = *ctx.bumps.get("dispute").unwrap();
+        dispute.created_at = now;
+        dispute.ruled_at = None;
+
+        market.status = MarketStatus::Disputed;
+
+        emit!(ResultChallengedEvent {
+            market: market.key(),
+            challenger: dispute.challenger,
+            proposed_outcome,
+            challenged_outcome: outcome_index,
+            bond,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    // Arbitrator ruling on a disputed result. An upheld challenge replaces the outcome and returns the bond;
+    // a rejected challenge keeps the proposed outcome and slashes the bond into the fee ledger.
+    pub fn rule_dispute(ctx: Context<RuleDispute>, upheld: bool) -> Result<()> {
+        ctx.accounts.platform.require_not_paused(PAUSE_SETTLEMENT)?;
+        let market = &mut ctx.accounts.market;
+        let dispute = &mut ctx.accounts.dispute;
+        let challenger = &mut ctx.accounts.challenger_user;
+        let platform = &mut ctx.accounts.platform;
+
+        require!(
+            market.status == MarketStatus::Disputed,
+            SportPredictError::MarketNotDisputed
+        );
+        require!(
+            dispute.status == DisputeStatus::Open,
+            SportPredictError::MarketNotDisputed
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        let winning_outcome = if upheld {
+            challenger.balance = math::add(challenger.balance, dispute.bond)?;
+            dispute.status = DisputeStatus::Upheld;
+            dispute.challenged_outcome
+        } else {
+            platform.total_slashed_bonds = math::add(platform.total_slashed_bonds, dispute.bond)?;
+            ctx.accounts.fee_ledger.accrue(dispute.bond)?;
+            dispute.status = DisputeStatus::Rejected;
+            dispute.proposed_outcome
+        };
+        dispute.ruled_at = Some(now);
+
+        market.resolve(platform, winning_outcome, now)?;
+
+        emit!(DisputeRuledEvent {
+            market: market.key(),
+            dispute: dispute.key(),
+            upheld,
+            bond: dispute.bond,
+            timestamp: now,
+        });
+        emit!(MarketResolvedEvent {
+            market: market.key(),
+            winning_outcome,
+            disputed: true,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    // Arbitrator ruling on a market whose oracle vote split too far to reach quorum
+    pub fn resolve_deadlock(ctx: Context<ResolveDeadlock>, outcome_index: u8) -> Result<()> {
+        ctx.accounts.platform.require_not_paused(PAUSE_SETTLEMENT)?;
+        let market = &mut ctx.accounts.market;
+        let platform = &mut ctx.accounts.platform;
+
+        require!(
+            market.status == MarketStatus::Deadlocked,
+            SportPredictError::MarketNotDeadlocked
+        );
+        require!(
+            (outcome_index as usize) < market.outcomes.len(),
+            SportPredictError::InvalidOutcome
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        market.resolve(platform, outcome_index, now)?;
+
+        emit!(MarketResolvedEvent {
+            market: market.key(),
+            winning_outcome: outcome_index,
+            disputed: true,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    // Finalize an unchallenged proposal once the dispute period has passed. Permissionless.
+    pub fn finalize_market(ctx: Context<FinalizeMarket>) -> Result<()> {
+        ctx.accounts.platform.require_not_paused(PAUSE_SETTLEMENT)?;
+        let market = &mut ctx.accounts.market;
+        let platform = &mut ctx.accounts.platform;
+
+        require!(
+            market.status == MarketStatus::Proposed,
+            SportPredictError::MarketNotProposed
+        );
+        let proposed_outcome = market
+            .proposed_outcome
+            .ok_or(SportPredictError::MarketNotProposed)?;
+        let proposed_at = market
+            .proposed_at
+            .ok_or(SportPredictError::MarketNotProposed)?;
+
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            now >= math::add_seconds(proposed_at, platform.dispute_period)?,
+            SportPredictError::DisputePeriodActive
+        );
+
+        market.resolve(platform, proposed_outcome, now)?;
+
+        emit!(MarketResolvedEvent {
+            market: market.key(),
+            winning_outcome: proposed_outcome,
+            disputed: false,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    // Settle a bet against its resolved market. Permissionless so a crank can claim on behalf of bettors.
+    pub fn claim_bet(ctx: Context<ClaimBet>) -> Result<()> {
+        ctx.accounts.platform.require_not_paused(PAUSE_SETTLEMENT)?;
+        let bet = &mut ctx.accounts.bet;
+        let user = &mut ctx.accounts.user;
+        let market = &mut ctx.accounts.market;
+        let platform = &mut ctx.accounts.platform;
+
+        require!(
+            bet.status == BetStatus::Pending,
+            SportPredictError::BetAlreadySettled
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        let fee = market.settle_bet(bet, user, platform, &mut ctx.accounts.fee_ledger, now)?;
+
+        if bet.result == Some(BetResult::Win) {
+            // Emit win event
+            emit!(BetWonEvent {
+                user: user.key(),
+                bet: bet.key(),
+                winnings: bet.winnings.unwrap_or(0),
+                fee,
+                timestamp: now,
+            });
+        } else {
+            // Emit loss event
+            emit!(BetLostEvent {
+                user: user.key(),
+                bet: bet.key(),
+                timestamp: now,
+            });
+        }
+
+        Ok(())
+    }
+
+    // Settle many bets on one resolved market. `remaining_accounts` holds (bet, user) pairs;
+    // bets that are no longer pending are skipped so overlapping cranks are harmless. Permissionless.
+    pub fn claim_bets<'info>(ctx: Context<'_, '_, '_, 'info, ClaimBets<'info>>) -> Result<()> {
+        ctx.accounts.platform.require_not_paused(PAUSE_SETTLEMENT)?;
+        require!(
+            !ctx.remaining_accounts.is_empty()
+                && ctx
+                    .remaining_accounts
+                    .chunks_exact(2)
+                    .remainder()
+                    .is_empty(),
+            SportPredictError::InvalidRemainingAccounts
+        );
+
+        let market = &mut ctx.accounts.market;
+        let platform = &mut ctx.accounts.platform;
+        let fee_ledger = &mut ctx.accounts.fee_ledger;
+        let market_key = market.key();
+        require!(
+            market.winning_outcome.is_some(),
+            SportPredictError::MarketNotResolved
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        let mut won: u32 = 0;
+        let mut lost: u32 = 0;
+        let mut skipped: u32 = 0;
+        let mut total_winnings: u64 = 0;
+        let mut total_fees: u64 = 0;
+
+        for pair in ctx.remaining_accounts.chunks(2) {
+            let mut bet = Account::<Bet>::try_from(&pair[0])?;
+            let mut user = Account::<User>::try_from(&pair[1])?;
+            bet.verify_address(pair[0].key, ctx.program_id)?;
+            user.verify_address(pair[1].key, ctx.program_id)?;
+            require_keys_eq!(bet.market, market_key, SportPredictError::MarketMismatch);
+            require_keys_eq!(user.key(), bet.user, SportPredictError::InvalidUserAccount);
+
+            if bet.status != BetStatus::Pending {
+                skipped += 1;
+                continue;
+            }
+
+            let fee = market.settle_bet(&mut bet, &mut user, platform, fee_ledger, now)?;
+            if bet.result == Some(BetResult::Win) {
+                let winnings = bet.winnings.unwrap_or(0);
+                emit!(BetWonEvent {
+                    user: user.key(),
+                    bet: bet.key(),
+                    winnings,
+                    fee,
+                    timestamp: now,
+                });
+                won += 1;
+                total_winnings = math::add(total_winnings, winnings)?;
+                total_fees = math::add(total_fees, fee)?;
+            } else {
+                emit!(BetLostEvent {
+                    user: user.key(),
+                    bet: bet.key(),
+                    timestamp: now,
+                });
+                lost += 1;
+            }
+
+            bet.exit(ctx.program_id)?;
+            user.exit(ctx.program_id)?;
+        }
+
+        emit!(BetsClaimedEvent {
+            market: market_key,
+            won,
+            lost,
+            skipped,
+            total_winnings,
+            total_fees,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    // Withdraw funds
+    pub fn withdraw(ctx: Context<Withdraw>, amount: u64) -> Result<()> {
+        ctx.accounts
+            .platform
+            .require_not_paused(PAUSE_WITHDRAWALS)?;
+        ctx.accounts.attestation.require_valid(
+            ctx.accounts.platform.min_kyc_level,
+            Clock::get()?.unix_timestamp,
+        )?;
+        let user = &mut ctx.accounts.user;
+        let platform = &ctx.accounts.platform;
+
+        require!(
+            user.balance >= amount,
+            SportPredictError::InsufficientBalance
+        );
+        require!(user.is_active, SportPredictError::UserInactive);
+
+        let fee = math::fee(amount, platform.withdrawal_fee_bps)?;
+        ctx.accounts.fee_ledger.accrue(fee)?;
+
+        // Transfer tokens from platform vault to user, net of the withdrawal fee
+        let platform_seeds: &[&[u8]] = &[b"platform", &[platform.bump]];
+        let signer_seeds = &[platform_seeds];
+        let transfer_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.platform_vault.to_account_info(),
+                to: ctx.accounts.user_token_account.to_account_info(),
+                authority: platform.to_account_info(),
+            },
+            signer_seeds,
+        );
+
+        token::transfer(transfer_ctx, math::sub(amount, fee)?)?;
+
+        // Update user balance
+        user.balance = math::sub(user.balance, amount)?;
+
+        // Emit withdrawal event
+        emit!(WithdrawEvent {
+            user: user.key(),
+            amount,
+            fee,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Create ETF
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_etf(
+        ctx: Context<CreateETF>,
+        nonce: u64,
+        name: String,
+        description: String,
+        strategy: String,
+        risk_level: u8,
+        min_investment: u64,
+        max_investment: u64,
+        annual_return: u32,
+        management_fee: u16,
+        performance_fee: u16,
+    ) -> Result<()> {
+        ctx.accounts.platform.require_not_paused(PAUSE_ETF)?;
+        let etf = &mut ctx.accounts.etf;
+        let manager = &mut ctx.accounts.manager;
+
+        require!(nonce == manager.etf_nonce, SportPredictError::InvalidNonce);
+        require!(
+            name.len() <= MAX_NAME_LEN
+                && strategy.len() <= MAX_NAME_LEN
+                && description.len() <= MAX_DESCRIPTION_LEN,
+            SportPredictError::NameTooLong
+        );
+        math::validate_bps(management_fee)?;
+        math::validate_bps(performance_fee)?;
+
+        etf.manager = manager.key();
+        etf.name = name;
+        etf.description = description;
+        etf.strategy = strategy;
+        etf.risk_level = risk_level;
+        etf.min_investment = min_investment;
+        etf.max_investment = max_investment;
+        etf.annual_return = annual_return;
+        etf.management_fee = management_fee;
+        etf.performance_fee = performance_fee;
+        etf.status = ETFStatus::Active;
+        etf.total_value = 0;
+        etf.total_shares = 0;
+        etf.total_investors = 0;
+        etf.current_return = 0;
+        etf.blocked_regions = Vec::new();
+        etf.nonce = nonce;
+        etf.bump = *ctx.bumps.get("etf").unwrap();
+        etf.created_at = Clock::get()?.unix_timestamp;
+
+        manager.etf_nonce = math::add(manager.etf_nonce, 1)?;
+
+        Ok(())
+    }
+
+    // Pause, reactivate or close an ETF
+    pub fn set_etf_status(ctx: Context<SetETFStatus>, status: ETFStatus) -> Result<()> {
+        let etf = &mut ctx.accounts.etf;
+        require!(
+            etf.status != ETFStatus::Closed,
+            SportPredictError::ETFInactive
+        );
+        etf.status = status;
+        Ok(())
+    }
+
+    // Invest in ETF
+    pub fn invest_in_etf(ctx: Context<InvestInETF>, amount: u64) -> Result<()> {
+        ctx.accounts.platform.require_not_paused(PAUSE_ETF)?;
+        ctx.accounts.attestation.require_valid(
+            ctx.accounts.platform.min_kyc_level,
+            Clock::get()?.unix_timestamp,
+        )?;
+        let user = &mut ctx.accounts.user;
+        let etf = &mut ctx.accounts.etf;
+        let investment = &mut ctx.accounts.investment;
+
+        require!(
+            user.balance >= amount,
+            SportPredictError::InsufficientBalance
+        );
+        require!(user.is_active, SportPredictError::UserInactive);
+        require!(
+            !user.is_excluded(Clock::get()?.unix_timestamp),
+            SportPredictError::SelfExcluded
+        );
+        require!(
+            etf.status == ETFStatus::Active,
+            SportPredictError::ETFInactive
+        );
+        require!(
+            !ctx.accounts
+                .attestation
+                .is_blocked(&ctx.accounts.platform, &etf.blocked_regions),
+            SportPredictError::RegionRestricted
+        );
+        require!(
+            amount >= etf.min_investment,
+            SportPredictError::InvestmentTooSmall
+        );
+        require!(
+            amount <= etf.max_investment,
+            SportPredictError::InvestmentTooLarge
+        );
+
+        // Shares are minted at the current share price, rounded down in the fund's favour
+        let shares = if etf.total_shares == 0 {
+            amount
+        } else {
+            math::mul_div_floor(amount, etf.total_shares, etf.total_value)?
+        };
+        require!(shares > 0, SportPredictError::InvestmentTooSmall);
+
+        // Create investment account
+        investment.user = user.key();
+        investment.etf = etf.key();
+        investment.amount = amount;
+        investment.shares = shares;
+        investment.status = InvestmentStatus::Active;
+        investment.bump = *ctx.bumps.get("investment").unwrap();
+        investment.created_at = Clock::get()?.unix_timestamp;
+
+        // Update user and ETF balances; the investment counts against the user's wager and loss limits
+        user.record_wager(amount, investment.created_at)?;
+        user.balance = math::sub(user.balance, amount)?;
+        etf.total_value = math::add(etf.total_value, amount)?;
+        etf.total_shares = math::add(etf.total_shares, shares)?;
+        etf.total_investors += 1;
+
+        // Emit investment event
+        emit!(ETFInvestmentEvent {
+            user: user.key(),
+            etf: etf.key(),
+            amount,
+            shares: investment.shares,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Redeem from ETF
+    pub fn redeem_from_etf(ctx: Context<RedeemFromETF>, shares: u64) -> Result<()> {
+        ctx.accounts.platform.require_not_paused(PAUSE_ETF)?;
+        let user = &mut ctx.accounts.user;
+        let etf = &mut ctx.accounts.etf;
+        let investment = &mut ctx.accounts.investment;
+
+        require!(
+            investment.shares >= shares,
+            SportPredictError::InsufficientShares
+        );
+        require!(
+            investment.status == InvestmentStatus::Active,
+            SportPredictError::InvestmentInactive
+        );
+
+        // Redeemed at the current share price, rounded down in the fund's favour
+        let redemption_amount = math::mul_div_floor(shares, etf.total_value, etf.total_shares)?;
+
+        // Update investment
+        investment.shares = math::sub(investment.shares, shares)?;
+        if investment.shares == 0 {
+            investment.status = InvestmentStatus::Redeemed;
+            etf.total_investors -= 1;
+        }
+
+        // Update user and ETF balances
+        user.balance = math::add(user.balance, redemption_amount)?;
+        user.record_return(redemption_amount, Clock::get()?.unix_timestamp)?;
+        etf.total_value = math::sub(etf.total_value, redemption_amount)?;
+        etf.total_shares = math::sub(etf.total_shares, shares)?;
+
+        // Emit redemption event
+        emit!(ETFRedeemEvent {
+            user: user.key(),
+            etf: etf.key(),
+            shares,
+            redemption_amount,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Close a settled, cancelled or fully cashed-out bet and return its rent to the bettor
+    pub fn close_bet(ctx: Context<CloseBet>) -> Result<()> {
+        require!(
+            ctx.accounts.bet.is_closable(),
+            SportPredictError::BetNotClosable
+        );
+        Ok(())
+    }
+
+    // Close a fully redeemed investment and return its rent to the investor
+    pub fn close_investment(ctx: Context<CloseInvestment>) -> Result<()> {
+        require!(
+            ctx.accounts.investment.is_closable(),
+            SportPredictError::InvestmentNotClosable
+        );
+        Ok(())
+    }
+
+    // Close many of the caller's finished bets, parlays and investments at once. `remaining_accounts`
+    // holds writable Bet, Parlay or Investment accounts in any mix; every one must be closable or nothing
+    // is closed.
+    pub fn close_accounts<'info>(
+        ctx: Context<'_, '_, '_, 'info, CloseAccounts<'info>>,
+    ) -> Result<()> {
+        require!(
+            !ctx.remaining_accounts.is_empty(),
+            SportPredictError::InvalidRemainingAccounts
+        );
+
+        let user_key = ctx.accounts.user.key();
+        let destination = ctx.accounts.authority.to_account_info();
+        let mut bets_closed: u32 = 0;
+        let mut parlays_closed: u32 = 0;
+        let mut investments_closed: u32 = 0;
+
+        for info in ctx.remaining_accounts.iter() {
+            if let Ok(bet) = Account::<Bet>::try_from(info) {
+                bet.verify_address(info.key, ctx.program_id)?;
+                require_keys_eq!(bet.user, user_key, SportPredictError::InvalidUserAccount);
+                require!(bet.is_closable(), SportPredictError::BetNotClosable);
+                bet.close(destination.clone())?;
+                bets_closed += 1;
+            } else if let Ok(parlay) = Account::<Parlay>::try_from(info) {
+                parlay.verify_address(info.key, ctx.program_id)?;
+                require_keys_eq!(parlay.user, user_key, SportPredictError::InvalidUserAccount);
+                require!(parlay.is_closable(), SportPredictError::ParlayNotClosable);
+                parlay.close(destination.clone())?;
+                parlays_closed += 1;
+            } else {
+                let investment = Account::<Investment>::try_from(info)?;
+                investment.verify_address(info.key, ctx.program_id)?;
+                require_keys_eq!(
+                    investment.user,
+                    user_key,
+                    SportPredictError::InvalidUserAccount
+                );
+                require!(
+                    investment.is_closable(),
+                    SportPredictError::InvestmentNotClosable
+                );
+                investment.close(destination.clone())?;
+                investments_closed += 1;
+            }
+        }
+
+        emit!(AccountsClosedEvent {
+            user: user_key,
+            bets_closed,
+            parlays_closed,
+            investments_closed,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Platform::INIT_SPACE,
+        seeds = [b"platform"],
+        bump
+    )]
+    pub platform: Account<'info, Platform>,
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + FeeLedger::INIT_SPACE,
+        seeds = [b"fee_ledger"],
+        bump
+    )]
+    pub fee_ledger: Account<'info, FeeLedger>,
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + RoleRegistry::INIT_SPACE,
+        seeds = [b"roles"],
+        bump
+    )]
+    pub roles: Account<'info, RoleRegistry>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdatePlatform<'info> {
+    #[account(
+        mut,
+        seeds = [b"platform"],
+        bump = platform.bump
+    )]
+    pub platform: Account<'info, Platform>,
+    #[account(
+        seeds = [b"roles"],
+        bump = roles.bump,
+        constraint = roles.has_role(&authority.key(), Role::Admin) @ SportPredictError::Unauthorized
+    )]
+    pub roles: Account<'info, RoleRegistry>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeAuthority<'info> {
+    #[account(
+        mut,
+        seeds = [b"platform"],
+        bump = platform.bump,
+        has_one = authority @ SportPredictError::Unauthorized
+    )]
+    pub platform: Account<'info, Platform>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptAuthority<'info> {
+    #[account(
+        mut,
+        seeds = [b"platform"],
+        bump = platform.bump,
+        constraint = platform.pending_authority == Some(new_authority.key()) @ SportPredictError::Unauthorized
+    )]
+    pub platform: Account<'info, Platform>,
+    #[account(
+        mut,
+        seeds = [b"roles"],
+        bump = roles.bump
+    )]
+    pub roles: Account<'info, RoleRegistry>,
+    pub new_authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(change: ParamChange)]
+pub struct ScheduleChange<'info> {
+    #[account(
+        init,
+        payer = proposer,
+        space = 8 + PendingChange::INIT_SPACE,
+        seeds = [b"pending_change", platform.change_count.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub pending_change: Account<'info, PendingChange>,
+    #[account(
+        mut,
+        seeds = [b"platform"],
+        bump = platform.bump
+    )]
+    pub platform: Account<'info, Platform>,
+    #[account(
+        seeds = [b"roles"],
+        bump = roles.bump,
+        constraint = roles.has_role(&proposer.key(), change.required_role()) @ SportPredictError::Unauthorized
+    )]
+    pub roles: Account<'info, RoleRegistry>,
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteChange<'info> {
+    #[account(
+        mut,
+        seeds = [b"pending_change", &pending_change.id.to_le_bytes()],
+        bump = pending_change.bump,
+        has_one = proposer,
+        close = proposer
+    )]
+    pub pending_change: Account<'info, PendingChange>,
+    #[account(
+        mut,
+        seeds = [b"platform"],
+        bump = platform.bump
+    )]
+    pub platform: Account<'info, Platform>,
+    #[account(
+        mut,
+        seeds = [b"roles"],
+        bump = roles.bump
+    )]
+    pub roles: Account<'info, RoleRegistry>,
+    #[account(mut)]
+    pub proposer: SystemAccount<'info>,
+    pub executor: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelChange<'info> {
+    #[account(
+        mut,
+        seeds = [b"pending_change", &pending_change.id.to_le_bytes()],
+        bump = pending_change.bump,
+        has_one = proposer,
+        close = proposer
+    )]
+    pub pending_change: Account<'info, PendingChange>,
+    #[account(
+        seeds = [b"roles"],
+        bump = roles.bump,
+        constraint = roles.has_role(&authority.key(), pending_change.change.required_role()) @ SportPredictError::Unauthorized
+    )]
+    pub roles: Account<'info, RoleRegistry>,
+    #[account(mut)]
+    pub proposer: SystemAccount<'info>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ManageRoles<'info> {
+    #[account(
+        mut,
+        seeds = [b"roles"],
+        bump = roles.bump,
+        constraint = roles.has_role(&authority.key(), Role::Admin) @ SportPredictError::Unauthorized
+    )]
+    pub roles: Account<'info, RoleRegistry>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetUserActive<'info> {
+    #[account(
+        mut,
+        seeds = [b"user", user.authority.as_ref()],
+        bump = user.bump
+    )]
+    pub user: Account<'info, User>,
+    #[account(
+        seeds = [b"roles"],
+        bump = roles.bump,
+        constraint = roles.has_role(&compliance_officer.key(), Role::ComplianceOfficer) @ SportPredictError::Unauthorized
+    )]
+    pub roles: Account<'info, RoleRegistry>,
+    pub compliance_officer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct LpDeposit<'info> {
+    #[account(
+        mut,
+        seeds = [b"platform"],
+        bump = platform.bump
+    )]
+    pub platform: Account<'info, Platform>,
+    #[account(
+        init_if_needed,
+        payer = provider,
+        space = 8 + LpPosition::INIT_SPACE,
+        seeds = [b"lp_position", provider.key().as_ref()],
+        bump
+    )]
+    pub lp_position: Account<'info, LpPosition>,
+    #[account(
+        mut,
+        seeds = [b"vault"],
+        bump
+    )]
+    pub platform_vault: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub provider_token_account: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub provider: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct LpWithdraw<'info> {
+    #[account(
+        mut,
+        seeds = [b"platform"],
+        bump = platform.bump
+    )]
+    pub platform: Account<'info, Platform>,
+    #[account(
+        mut,
+        seeds = [b"lp_position", provider.key().as_ref()],
+        bump = lp_position.bump,
+        has_one = provider
+    )]
+    pub lp_position: Account<'info, LpPosition>,
+    #[account(
+        mut,
+        seeds = [b"vault"],
+        bump
+    )]
+    pub platform_vault: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub provider_token_account: Account<'info, TokenAccount>,
+    pub provider: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawPlatformFees<'info> {
+    #[account(
+        seeds = [b"platform"],
+        bump = platform.bump,
+        has_one = authority @ SportPredictError::Unauthorized
+    )]
+    pub platform: Account<'info, Platform>,
+    #[account(
+        mut,
+        seeds = [b"fee_ledger"],
+        bump = fee_ledger.bump
+    )]
+    pub fee_ledger: Account<'info, FeeLedger>,
+    #[account(
+        mut,
+        seeds = [b"vault"],
+        bump
+    )]
+    pub platform_vault: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub authority_token_account: Account<'info, TokenAccount>,
+    pub authority: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+#[instruction(username: String)]
+pub struct CreateUser<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + User::INIT_SPACE,
+        seeds = [b"user", authority.key().as_ref()],
+        bump
+    )]
+    pub user: Account<'info, User>,
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + UsernameClaim::INIT_SPACE,
+        seeds = [b"username", username.as_bytes()],
+        bump
+    )]
+    pub username_claim: Account<'info, UsernameClaim>,
+    #[account(
+        mut,
+        seeds = [b"platform"],
+        bump = platform.bump
+    )]
+    pub platform: Account<'info, Platform>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(wallet: Pubkey)]
+pub struct IssueAttestation<'info> {
+    #[account(
+        init_if_needed,
+        payer = compliance_officer,
+        space = 8 + Attestation::INIT_SPACE,
+        seeds = [b"attestation", wallet.as_ref()],
+        bump
+    )]
+    pub attestation: Account<'info, Attestation>,
+    #[account(
+        seeds = [b"roles"],
+        bump = roles.bump,
+        constraint = roles.has_role(&compliance_officer.key(), Role::ComplianceOfficer) @ SportPredictError::Unauthorized
+    )]
+    pub roles: Account<'info, RoleRegistry>,
+    #[account(mut)]
+    pub compliance_officer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetDefaultBlockedRegions<'info> {
+    #[account(
+        mut,
+        seeds = [b"platform"],
+        bump = platform.bump
+    )]
+    pub platform: Account<'info, Platform>,
+    #[account(
+        seeds = [b"roles"],
+        bump = roles.bump,
+        constraint = roles.has_role(&compliance_officer.key(), Role::ComplianceOfficer) @ SportPredictError::Unauthorized
+    )]
+    pub roles: Account<'info, RoleRegistry>,
+    pub compliance_officer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetMarketBlockedRegions<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market.match_account.as_ref(), &[market.index]],
+        bump = market.bump
+    )]
+    pub market: Account<'info, Market>,
+    #[account(
+        seeds = [b"roles"],
+        bump = roles.bump,
+        constraint = roles.has_role(&compliance_officer.key(), Role::ComplianceOfficer) @ SportPredictError::Unauthorized
+    )]
+    pub roles: Account<'info, RoleRegistry>,
+    pub compliance_officer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetETFBlockedRegions<'info> {
+    #[account(
+        mut,
+        seeds = [b"etf", etf.manager.as_ref(), &etf.nonce.to_le_bytes()],
+        bump = etf.bump
+    )]
+    pub etf: Account<'info, ETF>,
+    #[account(
+        seeds = [b"roles"],
+        bump = roles.bump,
+        constraint = roles.has_role(&compliance_officer.key(), Role::ComplianceOfficer) @ SportPredictError::Unauthorized
+    )]
+    pub roles: Account<'info, RoleRegistry>,
+    pub compliance_officer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeAttestation<'info> {
+    #[account(
+        mut,
+        seeds = [b"attestation", attestation.wallet.as_ref()],
+        bump = attestation.bump,
+        close = compliance_officer
+    )]
+    pub attestation: Account<'info, Attestation>,
+    #[account(
+        seeds = [b"roles"],
+        bump = roles.bump,
+        constraint = roles.has_role(&compliance_officer.key(), Role::ComplianceOfficer) @ SportPredictError::Unauthorized
+    )]
+    pub roles: Account<'info, RoleRegistry>,
+    #[account(mut)]
+    pub compliance_officer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateUserSettings<'info> {
+    #[account(
+        mut,
+        seeds = [b"user", authority.key().as_ref()],
+        bump = user.bump
+    )]
+    pub user: Account<'info, User>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(new_username: String)]
+pub struct RenameUser<'info> {
+    #[account(
+        mut,
+        seeds = [b"user", authority.key().as_ref()],
+        bump = user.bump
+    )]
+    pub user: Account<'info, User>,
+    #[account(
+        mut,
+        seeds = [b"username", user.username.as_bytes()],
+        bump = old_username_claim.bump,
+        constraint = old_username_claim.user == user.key() @ SportPredictError::InvalidUserAccount,
+        close = authority
+    )]
+    pub old_username_claim: Account<'info, UsernameClaim>,
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + UsernameClaim::INIT_SPACE,
+        seeds = [b"username", new_username.as_bytes()],
+        bump
+    )]
+    pub new_username_claim: Account<'info, UsernameClaim>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Deposit<'info> {
+    #[account(
+        mut,
+        seeds = [b"user", authority.key().as_ref()],
+        bump = user.bump
+    )]
+    pub user: Account<'info, User>,
+    #[account(
+        mut,
+        seeds = [b"platform"],
+        bump = platform.bump
+    )]
+    pub platform: Account<'info, Platform>,
+    #[account(
+        seeds = [b"attestation", authority.key().as_ref()],
+        bump = attestation.bump
+    )]
+    pub attestation: Account<'info, Attestation>,
+    #[account(
+        mut,
+        seeds = [b"fee_ledger"],
+        bump = fee_ledger.bump
+    )]
+    pub fee_ledger: Account<'info, FeeLedger>,
+    #[account(
+        mut,
+        seeds = [b"vault"],
+        bump
+    )]
+    pub platform_vault: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub user_token_account: Account<'info, TokenAccount>,
+    pub authority: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+#[instruction(match_id: String)]
+pub struct CreateMatch<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Match::INIT_SPACE,
+        seeds = [b"match", match_id.as_bytes()],
+        bump
+    )]
+    pub match_account: Account<'info, Match>,
+    #[account(
+        seeds = [b"roles"],
+        bump = roles.bump,
+        constraint = roles.has_role(&authority.key(), Role::Admin) @ SportPredictError::Unauthorized
+    )]
+    pub roles: Account<'info, RoleRegistry>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateMatch<'info> {
+    #[account(
+        mut,
+        seeds = [b"match", match_account.match_id.as_bytes()],
+        bump = match_account.bump
+    )]
+    pub match_account: Account<'info, Match>,
+    #[account(
+        seeds = [b"roles"],
+        bump = roles.bump,
+        constraint = roles.has_role(&authority.key(), Role::Admin) @ SportPredictError::Unauthorized
+    )]
+    pub roles: Account<'info, RoleRegistry>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RefundCancelledBets<'info> {
+    #[account(
+        seeds = [b"match", match_account.match_id.as_bytes()],
+        bump = match_account.bump
+    )]
+    pub match_account: Account<'info, Match>,
+    #[account(
+        seeds = [b"platform"],
+        bump = platform.bump
+    )]
+    pub platform: Account<'info, Platform>,
+    pub cranker: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseMarketExposure<'info> {
+    #[account(
+        seeds = [b"match", match_account.match_id.as_bytes()],
+        bump = match_account.bump
+    )]
+    pub match_account: Account<'info, Match>,
+    #[account(
+        mut,
+        seeds = [b"market", market.match_account.as_ref(), &[market.index]],
+        bump = market.bump,
+        constraint = market.match_account == match_account.key() @ SportPredictError::MatchMismatch
+    )]
+    pub market: Account<'info, Market>,
+    #[account(
+        mut,
+        seeds = [b"platform"],
+        bump = platform.bump
+    )]
+    pub platform: Account<'info, Platform>,
+    pub cranker: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CreateMarket<'info> {
+    #[account(
+        mut,
+        seeds = [b"match", match_account.match_id.as_bytes()],
+        bump = match_account.bump
+    )]
+    pub match_account: Account<'info, Match>,
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Market::INIT_SPACE,
+        seeds = [b"market", match_account.key().as_ref(), &[match_account.market_count]],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    #[account(
+        seeds = [b"roles"],
+        bump = roles.bump,
+        constraint = roles.has_role(&authority.key(), Role::OddsSetter) @ SportPredictError::Unauthorized
+    )]
+    pub roles: Account<'info, RoleRegistry>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(nonce: u64)]
+pub struct PlaceParlay<'info> {
+    #[account(
+        mut,
+        seeds = [b"user", authority.key().as_ref()],
+        bump = user.bump
+    )]
+    pub user: Account<'info, User>,
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Parlay::INIT_SPACE,
+        seeds = [b"parlay", user.key().as_ref(), &nonce.to_le_bytes()],
+        bump
+    )]
+    pub parlay: Account<'info, Parlay>,
+    #[account(
+        mut,
+        seeds = [b"platform"],
+        bump = platform.bump
+    )]
+    pub platform: Account<'info, Platform>,
+    #[account(
+        seeds = [b"attestation", authority.key().as_ref()],
+        bump = attestation.bump
+    )]
+    pub attestation: Account<'info, Attestation>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SettleParlay<'info> {
+    #[account(
+        mut,
+        seeds = [b"parlay", parlay.user.as_ref(), &parlay.nonce.to_le_bytes()],
+        bump = parlay.bump
+    )]
+    pub parlay: Account<'info, Parlay>,
+    #[account(
+        mut,
+        seeds = [b"user", user.authority.as_ref()],
+        bump = user.bump,
+        constraint = parlay.user == user.key() @ SportPredictError::InvalidUserAccount
+    )]
+    pub user: Account<'info, User>,
+    #[account(
+        mut,
+        seeds = [b"platform"],
+        bump = platform.bump
+    )]
+    pub platform: Account<'info, Platform>,
+    #[account(
+        mut,
+        seeds = [b"fee_ledger"],
+        bump = fee_ledger.bump
+    )]
+    pub fee_ledger: Account<'info, FeeLedger>,
+    pub cranker: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelBet<'info> {
+    #[account(
+        mut,
+        seeds = [b"bet", user.key().as_ref(), &bet.nonce.to_le_bytes()],
+        bump = bet.bump,
+        constraint = bet.user == user.key() @ SportPredictError::InvalidUserAccount
+    )]
+    pub bet: Account<'info, Bet>,
+    #[account(
+        mut,
+        seeds = [b"user", authority.key().as_ref()],
+        bump = user.bump
+    )]
+    pub user: Account<'info, User>,
+    #[account(
+        seeds = [b"match", match_account.match_id.as_bytes()],
+        bump = match_account.bump,
+        constraint = bet.match_account == match_account.key() @ SportPredictError::MatchMismatch
+    )]
+    pub match_account: Account<'info, Match>,
+    #[account(
+        mut,
+        seeds = [b"market", market.match_account.as_ref(), &[market.index]],
+        bump = market.bump,
+        constraint = bet.market == market.key() @ SportPredictError::MarketMismatch
+    )]
+    pub market: Account<'info, Market>,
+    #[account(
+        mut,
+        seeds = [b"platform"],
+        bump = platform.bump
+    )]
+    pub platform: Account<'info, Platform>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CashOut<'info> {
+    #[account(
+        mut,
+        seeds = [b"bet", user.key().as_ref(), &bet.nonce.to_le_bytes()],
+        bump = bet.bump,
+        constraint = bet.user == user.key() @ SportPredictError::InvalidUserAccount
+    )]
+    pub bet: Account<'info, Bet>,
+    #[account(
+        mut,
+        seeds = [b"user", authority.key().as_ref()],
+        bump = user.bump
+    )]
+    pub user: Account<'info, User>,
+    #[account(
+        seeds = [b"match", match_account.match_id.as_bytes()],
+        bump = match_account.bump,
+        constraint = bet.match_account == match_account.key() @ SportPredictError::MatchMismatch
+    )]
+    pub match_account: Account<'info, Match>,
+    #[account(
+        mut,
+        seeds = [b"market", market.match_account.as_ref(), &[market.index]],
+        bump = market.bump,
+        constraint = bet.market == market.key() @ SportPredictError::MarketMismatch
+    )]
+    pub market: Account<'info, Market>,
+    #[account(
+        mut,
+        seeds = [b"platform"],
+        bump = platform.bump
+    )]
+    pub platform: Account<'info, Platform>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetOdds<'info> {
+    #[account(
+        seeds = [b"match", match_account.match_id.as_bytes()],
+        bump = match_account.bump
+    )]
+    pub match_account: Account<'info, Match>,
+    #[account(
+        mut,
+        seeds = [b"market", market.match_account.as_ref(), &[market.index]],
+        bump = market.bump,
+        constraint = market.match_account == match_account.key() @ SportPredictError::MatchMismatch
+    )]
+    pub market: Account<'info, Market>,
+    #[account(
+        seeds = [b"roles"],
+        bump = roles.bump,
+        constraint = roles.has_role(&odds_setter.key(), Role::OddsSetter) @ SportPredictError::Unauthorized
+    )]
+    pub roles: Account<'info, RoleRegistry>,
+    pub odds_setter: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(nonce: u64)]
+pub struct PlaceBet<'info> {
+    #[account(
+        mut,
+        seeds = [b"user", authority.key().as_ref()],
+        bump = user.bump
+    )]
+    pub user: Account<'info, User>,
+    #[account(
+        init,
         payer = authority,
-        space = 8 + Platform::INIT_SPACE,
-        seeds = [b"platform"],
+        space = 8 + Bet::INIT_SPACE,
+        seeds = [b"bet", user.key().as_ref(), &nonce.to_le_bytes()],
         bump
     )]
+    pub bet: Account<'info, Bet>,
+    #[account(
+        seeds = [b"match", match_account.match_id.as_bytes()],
+        bump = match_account.bump
+    )]
+    pub match_account: Account<'info, Match>,
+    #[account(
+        mut,
+        seeds = [b"market", market.match_account.as_ref(), &[market.index]],
+        bump = market.bump,
+        constraint = market.match_account == match_account.key() @ SportPredictError::MatchMismatch
+    )]
+    pub market: Account<'info, Market>,
+    #[account(
+        mut,
+        seeds = [b"platform"],
+        bump = platform.bump
+    )]
     pub platform: Account<'info, Platform>,
+    #[account(
+        seeds = [b"attestation", authority.key().as_ref()],
+        bump = attestation.bump
+    )]
+    pub attestation: Account<'info, Attestation>,
     #[account(mut)]
     pub authority: Signer<'info>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct CreateUser<'info> {
+pub struct SubmitResult<'info> {
+    #[account(
+        seeds = [b"match", match_account.match_id.as_bytes()],
+        bump = match_account.bump
+    )]
+    pub match_account: Account<'info, Match>,
+    #[account(
+        mut,
+        seeds = [b"market", market.match_account.as_ref(), &[market.index]],
+        bump = market.bump,
+        constraint = market.match_account == match_account.key() @ SportPredictError::MatchMismatch
+    )]
+    pub market: Account<'info, Market>,
     #[account(
         init,
-        payer = authority,
-        space = 8 + User::INIT_SPACE,
-        seeds = [b"user", authority.key().as_ref()],
+        payer = oracle,
+        space = 8 + OracleVote::INIT_SPACE,
+        seeds = [b"oracle_vote", market.key().as_ref(), oracle.key().as_ref()],
         bump
     )]
-    pub user: Account<'info, User>,
+    pub oracle_vote: Account<'info, OracleVote>,
+    #[account(
+        seeds = [b"roles"],
+        bump = roles.bump,
+        constraint = roles.has_role(&oracle.key(), Role::Oracle) @ SportPredictError::Unauthorized
+    )]
+    pub roles: Account<'info, RoleRegistry>,
     #[account(
-        mut,
         seeds = [b"platform"],
         bump = platform.bump
     )]
     pub platform: Account<'info, Platform>,
     #[account(mut)]
-    pub authority: Signer<'info>,
+    pub oracle: Signer<'info>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct Deposit<'info> {
+pub struct ChallengeResult<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market.match_account.as_ref(), &[market.index]],
+        bump = market.bump
+    )]
+    pub market: Account<'info, Market>,
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Dispute::INIT_SPACE,
+        seeds = [b"dispute", market.key().as_ref()],
+        bump
+    )]
+    pub dispute: Account<'info, Dispute>,
     #[account(
         mut,
         seeds = [b"user", authority.key().as_ref()],
         bump = user.bump
     )]
     pub user: Account<'info, User>,
+    #[account(
+        seeds = [b"platform"],
+        bump = platform.bump
+    )]
+    pub platform: Account<'info, Platform>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RuleDispute<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market.match_account.as_ref(), &[market.index]],
+        bump = market.bump
+    )]
+    pub market: Account<'info, Market>,
+    #[account(
+        mut,
+        seeds = [b"dispute", market.key().as_ref()],
+        bump = dispute.bump
+    )]
+    pub dispute: Account<'info, Dispute>,
+    #[account(
+        mut,
+        seeds = [b"user", dispute.challenger.as_ref()],
+        bump = challenger_user.bump
+    )]
+    pub challenger_user: Account<'info, User>,
     #[account(
         mut,
         seeds = [b"platform"],
@@ -362,54 +3014,79 @@ pub struct Deposit<'info> {
     pub platform: Account<'info, Platform>,
     #[account(
         mut,
-        seeds = [b"vault"],
-        bump
+        seeds = [b"fee_ledger"],
+        bump = fee_ledger.bump
     )]
-    pub platform_vault: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub user_token_account: Account<'info, TokenAccount>,
-    pub authority: Signer<'info>,
-    pub token_program: Program<'info, Token>,
+    pub fee_ledger: Account<'info, FeeLedger>,
+    #[account(
+        seeds = [b"roles"],
+        bump = roles.bump,
+        constraint = roles.has_role(&arbitrator.key(), Role::Arbitrator) @ SportPredictError::Unauthorized
+    )]
+    pub roles: Account<'info, RoleRegistry>,
+    pub arbitrator: Signer<'info>,
 }
 
 #[derive(Accounts)]
-pub struct PlaceBet<'info> {
+pub struct ResolveDeadlock<'info> {
     #[account(
         mut,
-        seeds = [b"user", authority.key().as_ref()],
-        bump = user.bump
+        seeds = [b"market", market.match_account.as_ref(), &[market.index]],
+        bump = market.bump
     )]
-    pub user: Account<'info, User>,
+    pub market: Account<'info, Market>,
     #[account(
-        init,
-        payer = authority,
-        space = 8 + Bet::INIT_SPACE,
-        seeds = [b"bet", user.key().as_ref(), &Clock::get()?.unix_timestamp.to_le_bytes()],
-        bump
+        mut,
+        seeds = [b"platform"],
+        bump = platform.bump
     )]
-    pub bet: Account<'info, Bet>,
+    pub platform: Account<'info, Platform>,
+    #[account(
+        seeds = [b"roles"],
+        bump = roles.bump,
+        constraint = roles.has_role(&arbitrator.key(), Role::Arbitrator) @ SportPredictError::Unauthorized
+    )]
+    pub roles: Account<'info, RoleRegistry>,
+    pub arbitrator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct FinalizeMarket<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market.match_account.as_ref(), &[market.index]],
+        bump = market.bump
+    )]
+    pub market: Account<'info, Market>,
     #[account(
         mut,
         seeds = [b"platform"],
         bump = platform.bump
     )]
     pub platform: Account<'info, Platform>,
-    pub authority: Signer<'info>,
-    pub system_program: Program<'info, System>,
+    pub cranker: Signer<'info>,
 }
 
 #[derive(Accounts)]
-pub struct SettleBet<'info> {
+pub struct ClaimBet<'info> {
     #[account(
         mut,
-        seeds = [b"bet", bet.user.as_ref(), &bet.created_at.to_le_bytes()],
+        seeds = [b"bet", bet.user.as_ref(), &bet.nonce.to_le_bytes()],
         bump = bet.bump
     )]
     pub bet: Account<'info, Bet>,
     #[account(
         mut,
-        seeds = [b"user", bet.user.as_ref()],
-        bump = user.bump
+        seeds = [b"market", market.match_account.as_ref(), &[market.index]],
+        bump = market.bump,
+        constraint = bet.market == market.key() @ SportPredictError::MarketMismatch
+    )]
+    pub market: Account<'info, Market>,
+    #[account(
+        mut,
+        seeds = [b"user", user.authority.as_ref()],
+        bump = user.bump,
+        constraint = bet.user == user.key() @ SportPredictError::InvalidUserAccount
     )]
     pub user: Account<'info, User>,
     #[account(
@@ -418,7 +3095,36 @@ pub struct SettleBet<'info> {
         bump = platform.bump
     )]
     pub platform: Account<'info, Platform>,
-    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"fee_ledger"],
+        bump = fee_ledger.bump
+    )]
+    pub fee_ledger: Account<'info, FeeLedger>,
+    pub cranker: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimBets<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market.match_account.as_ref(), &[market.index]],
+        bump = market.bump
+    )]
+    pub market: Account<'info, Market>,
+    #[account(
+        mut,
+        seeds = [b"platform"],
+        bump = platform.bump
+    )]
+    pub platform: Account<'info, Platform>,
+    #[account(
+        mut,
+        seeds = [b"fee_ledger"],
+        bump = fee_ledger.bump
+    )]
+    pub fee_ledger: Account<'info, FeeLedger>,
+    pub cranker: Signer<'info>,
 }
 
 #[derive(Accounts)]
@@ -435,6 +3141,17 @@ pub struct Withdraw<'info> {
         bump = platform.bump
     )]
     pub platform: Account<'info, Platform>,
+    #[account(
+        seeds = [b"attestation", authority.key().as_ref()],
+        bump = attestation.bump
+    )]
+    pub attestation: Account<'info, Attestation>,
+    #[account(
+        mut,
+        seeds = [b"fee_ledger"],
+        bump = fee_ledger.bump
+    )]
+    pub fee_ledger: Account<'info, FeeLedger>,
     #[account(
         mut,
         seeds = [b"vault"],
@@ -448,26 +3165,55 @@ pub struct Withdraw<'info> {
 }
 
 #[derive(Accounts)]
+#[instruction(nonce: u64)]
 pub struct CreateETF<'info> {
     #[account(
         init,
-        payer = manager,
+        payer = manager_signer,
         space = 8 + ETF::INIT_SPACE,
-        seeds = [b"etf", manager.key().as_ref(), &Clock::get()?.unix_timestamp.to_le_bytes()],
+        seeds = [b"etf", manager.key().as_ref(), &nonce.to_le_bytes()],
         bump
     )]
     pub etf: Account<'info, ETF>,
     #[account(
         mut,
-        seeds = [b"user", manager.key().as_ref()],
+        seeds = [b"user", manager_signer.key().as_ref()],
         bump = manager.bump
     )]
     pub manager: Account<'info, User>,
+    #[account(
+        seeds = [b"platform"],
+        bump = platform.bump
+    )]
+    pub platform: Account<'info, Platform>,
+    #[account(
+        seeds = [b"roles"],
+        bump = roles.bump,
+        constraint = roles.has_role(&manager_signer.key(), Role::EtfCurator) @ SportPredictError::Unauthorized
+    )]
+    pub roles: Account<'info, RoleRegistry>,
     #[account(mut)]
     pub manager_signer: Signer<'info>,
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+pub struct SetETFStatus<'info> {
+    #[account(
+        mut,
+        seeds = [b"etf", etf.manager.as_ref(), &etf.nonce.to_le_bytes()],
+        bump = etf.bump
+    )]
+    pub etf: Account<'info, ETF>,
+    #[account(
+        seeds = [b"roles"],
+        bump = roles.bump,
+        constraint = roles.has_role(&authority.key(), Role::EtfCurator) @ SportPredictError::Unauthorized
+    )]
+    pub roles: Account<'info, RoleRegistry>,
+    pub authority: Signer<'info>,
+}
+
 #[derive(Accounts)]
 pub struct InvestInETF<'info> {
     #[account(
@@ -478,7 +3224,7 @@ pub struct InvestInETF<'info> {
     pub user: Account<'info, User>,
     #[account(
         mut,
-        seeds = [b"etf", etf.manager.as_ref(), &etf.created_at.to_le_bytes()],
+        seeds = [b"etf", etf.manager.as_ref(), &etf.nonce.to_le_bytes()],
         bump = etf.bump
     )]
     pub etf: Account<'info, ETF>,
@@ -490,6 +3236,17 @@ pub struct InvestInETF<'info> {
         bump
     )]
     pub investment: Account<'info, Investment>,
+    #[account(
+        seeds = [b"platform"],
+        bump = platform.bump
+    )]
+    pub platform: Account<'info, Platform>,
+    #[account(
+        seeds = [b"attestation", authority.key().as_ref()],
+        bump = attestation.bump
+    )]
+    pub attestation: Account<'info, Attestation>,
+    #[account(mut)]
     pub authority: Signer<'info>,
     pub system_program: Program<'info, System>,
 }
@@ -504,7 +3261,7 @@ pub struct RedeemFromETF<'info> {
     pub user: Account<'info, User>,
     #[account(
         mut,
-        seeds = [b"etf", etf.manager.as_ref(), &etf.created_at.to_le_bytes()],
+        seeds = [b"etf", etf.manager.as_ref(), &etf.nonce.to_le_bytes()],
         bump = etf.bump
     )]
     pub etf: Account<'info, ETF>,
@@ -514,6 +3271,60 @@ pub struct RedeemFromETF<'info> {
         bump = investment.bump
     )]
     pub investment: Account<'info, Investment>,
+    #[account(
+        seeds = [b"platform"],
+        bump = platform.bump
+    )]
+    pub platform: Account<'info, Platform>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseBet<'info> {
+    #[account(
+        mut,
+        seeds = [b"bet", user.key().as_ref(), &bet.nonce.to_le_bytes()],
+        bump = bet.bump,
+        constraint = bet.user == user.key() @ SportPredictError::InvalidUserAccount,
+        close = authority
+    )]
+    pub bet: Account<'info, Bet>,
+    #[account(
+        seeds = [b"user", authority.key().as_ref()],
+        bump = user.bump
+    )]
+    pub user: Account<'info, User>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseInvestment<'info> {
+    #[account(
+        mut,
+        seeds = [b"investment", user.key().as_ref(), investment.etf.as_ref()],
+        bump = investment.bump,
+        constraint = investment.user == user.key() @ SportPredictError::InvalidUserAccount,
+        close = authority
+    )]
+    pub investment: Account<'info, Investment>,
+    #[account(
+        seeds = [b"user", authority.key().as_ref()],
+        bump = user.bump
+    )]
+    pub user: Account<'info, User>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseAccounts<'info> {
+    #[account(
+        seeds = [b"user", authority.key().as_ref()],
+        bump = user.bump
+    )]
+    pub user: Account<'info, User>,
+    #[account(mut)]
     pub authority: Signer<'info>,
 }
 
@@ -521,6 +3332,41 @@ pub struct RedeemFromETF<'info> {
 #[derive(InitSpace)]
 pub struct Platform {
     pub authority: Pubkey,
+    // Nominated successor; the transfer completes when they accept
+    pub pending_authority: Option<Pubkey>,
+    // Minimum attestation KYC level for deposits, bets, withdrawals and ETF investments
+    pub min_kyc_level: u8,
+    // Seconds a scheduled parameter change waits before it can be executed
+    pub timelock_delay: i64,
+    // Ids for scheduled changes
+    pub change_count: u64,
+    // Seconds a proposed result stays open to challenges
+    pub dispute_period: i64,
+    pub challenge_bond: u64,
+    pub total_slashed_bonds: u64,
+    // Seconds before kickoff after which bettors can no longer cancel
+    pub bet_cancel_cutoff: i64,
+    pub cancellation_fee_bps: u16,
+    pub cash_out_margin_bps: u16,
+    // Seconds after an odds update that in-play cash-outs may still be quoted from those odds
+    pub max_odds_age: i64,
+    // LP-owned house pool: the share of the vault's tokens that backs bettor payouts
+    pub house_reserve: u64,
+    // Profit owed to winners of resolved markets, moved out of the house reserve until claimed
+    pub winnings_payable: u64,
+    pub total_lp_shares: u64,
+    // Worst-case house loss across open markets and parlays
+    pub total_exposure: u64,
+    pub max_market_exposure: u64,
+    pub max_platform_exposure: u64,
+    pub winnings_fee_bps: u16,
+    pub deposit_fee_bps: u16,
+    pub withdrawal_fee_bps: u16,
+    // Regions blocked on every market and ETF
+    #[max_len(96)]
+    pub default_blocked_regions: Vec<[u8; 6]>,
+    // Bitset of PAUSE_* flags
+    pub pause_flags: u8,
     pub bump: u8,
     pub total_users: u64,
     pub total_bets: u64,
@@ -531,6 +3377,7 @@ pub struct Platform {
 #[derive(InitSpace)]
 pub struct User {
     pub authority: Pubkey,
+    #[max_len(32)]
     pub username: String,
     pub bump: u8,
     pub balance: u64,
@@ -538,20 +3385,194 @@ pub struct User {
     pub total_wins: u64,
     pub total_volume: u64,
     pub is_active: bool,
+    // Next seed for this user's bets/parlays and ETFs
+    pub bet_nonce: u64,
+    pub etf_nonce: u64,
+    // Self-imposed responsible-gambling limits and usage in the current windows
+    pub limits: GamblingLimits,
+    // Self-exclusion end; 0 when not excluded, i64::MAX when permanent
+    pub excluded_until: i64,
+}
+
+// Issued by a compliance officer once the wallet's owner passes the off-chain KYC check
+#[account]
+#[derive(InitSpace)]
+pub struct Attestation {
+    pub wallet: Pubkey,
+    pub kyc_level: u8,
+    pub expires_at: i64,
+    pub age_verified: bool,
+    // Regions the holder was verified in
+    #[max_len(4)]
+    pub regions: Vec<[u8; 6]>,
+    pub issued_by: Pubkey,
+    pub issued_at: i64,
+    pub bump: u8,
+}
+
+// Marks a username as taken; seeded by the name itself
+#[account]
+#[derive(InitSpace)]
+pub struct UsernameClaim {
+    pub user: Pubkey,
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct FeeLedger {
+    // Collected and not yet withdrawn
+    pub accrued_fees: u64,
+    pub total_collected: u64,
+    pub total_withdrawn: u64,
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct LpPosition {
+    pub provider: Pubkey,
+    pub shares: u64,
+    pub total_deposited: u64,
+    pub last_deposit: i64,
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Match {
+    #[max_len(32)]
+    pub match_id: String,
+    #[max_len(64)]
+    pub home_team: String,
+    #[max_len(64)]
+    pub away_team: String,
+    #[max_len(64)]
+    pub league: String,
+    #[max_len(64)]
+    pub sport: String,
+    pub start_time: i64,
+    pub end_time: Option<i64>,
+    pub status: MatchStatus,
+    pub market_count: u8,
+    pub created_by: Pubkey,
+    pub bump: u8,
+    pub created_at: i64,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Market {
+    pub match_account: Pubkey,
+    pub index: u8,
+    pub market_type: MarketType,
+    #[max_len(32)]
+    pub outcomes: Vec<MarketOutcome>,
+    // One entry per oracle that has voted on the result, bounded like the role registry
+    #[max_len(32)]
+    pub ballots: Vec<OracleBallot>,
+    // Bumped on every odds update so clients can detect stale quotes
+    pub odds_version: u64,
+    pub odds_updated_at: i64,
+    pub total_stake: u64,
+    // Worst-case house loss while open; profit still owed to winners once resolved
+    pub exposure: u64,
+    // Regions whose users may not bet on this market, in addition to the platform default
+    #[max_len(16)]
+    pub blocked_regions: Vec<[u8; 6]>,
+    pub status: MarketStatus,
+    pub proposed_outcome: Option<u8>,
+    pub proposed_at: Option<i64>,
+    pub winning_outcome: Option<u8>,
+    pub resolved_at: Option<i64>,
+    pub bump: u8,
+    pub created_at: i64,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct PendingChange {
+    pub id: u64,
+    pub change: ParamChange,
+    pub proposer: Pubkey,
+    pub scheduled_at: i64,
+    // Earliest time the change can be executed
+    pub eta: i64,
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct RoleRegistry {
+    #[max_len(32)]
+    pub grants: Vec<RoleGrant>,
+    // Number of oracle-role holders that must agree on a result
+    pub oracle_quorum: u8,
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct OracleVote {
+    pub market: Pubkey,
+    pub oracle: Pubkey,
+    pub outcome_index: u8,
+    pub bump: u8,
+    pub submitted_at: i64,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Dispute {
+    pub market: Pubkey,
+    pub challenger: Pubkey,
+    pub proposed_outcome: u8,
+    pub challenged_outcome: u8,
+    pub bond: u64,
+    pub status: DisputeStatus,
+    pub bump: u8,
+    pub created_at: i64,
+    pub ruled_at: Option<i64>,
 }
 
 #[account]
 #[derive(InitSpace)]
 pub struct Bet {
     pub user: Pubkey,
-    pub match_id: String,
-    pub prediction: String,
+    pub match_account: Pubkey,
+    pub market: Pubkey,
+    pub outcome_index: u8,
+    pub amount: u64,
+    // Decimal odds in 1e-4 units (see math::ODDS_SCALE)
+    pub odds: u32,
+    pub potential_winnings: u64,
+    // Total paid out through cash-outs so far
+    pub cashed_out: u64,
+    pub winnings: Option<u64>,
+    pub status: BetStatus,
+    pub result: Option<BetResult>,
+    // The owner's bet_nonce when placed; seeds the address
+    pub nonce: u64,
+    pub bump: u8,
+    pub created_at: i64,
+    pub settled_at: Option<i64>,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Parlay {
+    pub user: Pubkey,
+    #[max_len(8)]
+    pub legs: Vec<ParlayLeg>,
     pub amount: u64,
-    pub odds: f64,
+    // Product of the non-void legs' odds in 1e-4 units
+    pub combined_odds: u64,
     pub potential_winnings: u64,
     pub winnings: Option<u64>,
     pub status: BetStatus,
     pub result: Option<BetResult>,
+    // The owner's bet_nonce when placed; seeds the address
+    pub nonce: u64,
     pub bump: u8,
     pub created_at: i64,
     pub settled_at: Option<i64>,
@@ -561,56 +3582,997 @@ pub struct Bet {
 #[derive(InitSpace)]
 pub struct ETF {
     pub manager: Pubkey,
+    #[max_len(64)]
     pub name: String,
+    #[max_len(256)]
     pub description: String,
+    #[max_len(64)]
     pub strategy: String,
     pub risk_level: u8,
     pub min_investment: u64,
     pub max_investment: u64,
-    pub annual_return: f64,
-    pub management_fee: f64,
-    pub performance_fee: f64,
+    // Target return and fees in basis points
+    pub annual_return: u32,
+    pub management_fee: u16,
+    pub performance_fee: u16,
     pub status: ETFStatus,
     pub total_value: u64,
+    pub total_shares: u64,
     pub total_investors: u64,
-    pub current_return: f64,
+    // Basis points, negative when the fund is down
+    pub current_return: i32,
+    // Regions whose users may not invest, in addition to the platform default
+    #[max_len(16)]
+    pub blocked_regions: Vec<[u8; 6]>,
+    // The manager's etf_nonce when created; seeds the address
+    pub nonce: u64,
+    pub bump: u8,
+    pub created_at: i64,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Investment {
+    pub user: Pubkey,
+    pub etf: Pubkey,
+    pub amount: u64,
+    pub shares: u64,
+    pub status: InvestmentStatus,
     pub bump: u8,
     pub created_at: i64,
 }
 
-#[account]
-#[derive(InitSpace)]
-pub struct Investment {
-    pub user: Pubkey,
-    pub etf: Pubkey,
-    pub amount: u64,
-    pub shares: u64,
-    pub status: InvestmentStatus,
-    pub bump: u8,
-    pub created_at: i64,
+impl User {
+    // Usernames are stored in normalised form: lowercase ASCII letters, digits and underscores.
+    // Clients lowercase names before submitting so case variants cannot claim separate PDAs.
+    pub fn validate_username(username: &str) -> Result<()> {
+        require!(
+            username.len() >= MIN_USERNAME_LEN,
+            SportPredictError::UsernameTooShort
+        );
+        require!(
+            username.len() <= MAX_USERNAME_LEN,
+            SportPredictError::UsernameTooLong
+        );
+        require!(
+            username
+                .bytes()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == b'_'),
+            SportPredictError::InvalidUsername
+        );
+        Ok(())
+    }
+
+    // Self-exclusion is lifted only once `excluded_until` has passed
+    pub fn is_excluded(&self, now: i64) -> bool {
+        now < self.excluded_until
+    }
+
+    // Count a deposit against the deposit limit of every window
+    pub fn record_deposit(&mut self, amount: u64, now: i64) -> Result<()> {
+        for window in self.limits.rolled(now) {
+            let deposited = math::add(window.deposited, amount)?;
+            require!(
+                deposited <= window.deposit_limit.current(now),
+                SportPredictError::DepositLimitExceeded
+            );
+            window.deposited = deposited;
+        }
+        Ok(())
+    }
+
+    // Count a stake or investment against the wager and loss limits of every window.
+    // The loss limit assumes the whole amount could be lost.
+    pub fn record_wager(&mut self, amount: u64, now: i64) -> Result<()> {
+        for window in self.limits.rolled(now) {
+            let wagered = math::add(window.wagered, amount)?;
+            require!(
+                wagered <= window.wager_limit.current(now),
+                SportPredictError::WagerLimitExceeded
+            );
+            require!(
+                math::add(window.net_loss(), amount)? <= window.loss_limit.current(now),
+                SportPredictError::LossLimitExceeded
+            );
+            window.wagered = wagered;
+        }
+        Ok(())
+    }
+
+    // Credit winnings, refunds, cash-outs and redemptions against the loss limit
+    pub fn record_return(&mut self, amount: u64, now: i64) -> Result<()> {
+        for window in self.limits.rolled(now) {
+            window.returned = math::add(window.returned, amount)?;
+        }
+        Ok(())
+    }
+
+    // Re-derives the user PDA for accounts passed through `remaining_accounts`
+    pub fn verify_address(&self, key: &Pubkey, program_id: &Pubkey) -> Result<()> {
+        let expected = Pubkey::create_program_address(
+            &[b"user", self.authority.as_ref(), &[self.bump]],
+            program_id,
+        )
+        .map_err(|_| error!(SportPredictError::InvalidUserAccount))?;
+        require_keys_eq!(expected, *key, SportPredictError::InvalidUserAccount);
+        Ok(())
+    }
+}
+
+impl Attestation {
+    // Accepts an ISO 3166-1 alpha-2 country code (CN) or an ISO 3166-2 subdivision code (IN-AP)
+    pub fn parse_region(code: &str) -> Result<RegionCode> {
+        let bytes = code.as_bytes();
+        let valid = match bytes.len() {
+            2 => bytes.iter().all(u8::is_ascii_uppercase),
+            4..=REGION_CODE_LEN => {
+                bytes[..2].iter().all(u8::is_ascii_uppercase)
+                    && bytes[2] == b'-'
+                    && bytes[3..]
+                        .iter()
+                        .all(|b| b.is_ascii_uppercase() || b.is_ascii_digit())
+            }
+            _ => false,
+        };
+        require!(valid, SportPredictError::InvalidRegionCode);
+
+        let mut region = [0u8; REGION_CODE_LEN];
+        region[..bytes.len()].copy_from_slice(bytes);
+        Ok(region)
+    }
+
+    // Parses a list of region codes, dropping repeats
+    pub fn parse_regions(codes: &[String], max: usize) -> Result<Vec<RegionCode>> {
+        require!(codes.len() <= max, SportPredictError::TooManyRegionCodes);
+        let mut regions = Vec::with_capacity(codes.len());
+        for code in codes {
+            let region = Self::parse_region(code)?;
+            if !regions.contains(&region) {
+                regions.push(region);
+            }
+        }
+        Ok(regions)
+    }
+
+    // A blocked country covers all of its subdivisions, and a holder attested only at country level
+    // is treated as being in any of them
+    pub fn is_blocked_in(&self, blocked_regions: &[RegionCode]) -> bool {
+        self.regions.iter().any(|region| {
+            let subdivision_unknown = !self
+                .regions
+                .iter()
+                .any(|other| other[..2] == region[..2] && other[2] != 0);
+            blocked_regions.iter().any(|blocked| {
+                region[..2] == blocked[..2]
+                    && (region == blocked || blocked[2] == 0 || subdivision_unknown)
+            })
+        })
+    }
+
+    // Checks the platform default as well as a market's or ETF's own blocks
+    pub fn is_blocked(&self, platform: &Platform, extra_blocked_regions: &[RegionCode]) -> bool {
+        self.is_blocked_in(&platform.default_blocked_regions)
+            || self.is_blocked_in(extra_blocked_regions)
+    }
+
+    pub fn require_valid(&self, min_kyc_level: u8, now: i64) -> Result<()> {
+        require!(now < self.expires_at, SportPredictError::AttestationExpired);
+        require!(self.age_verified, SportPredictError::AgeNotVerified);
+        require!(
+            self.kyc_level >= min_kyc_level,
+            SportPredictError::KycLevelTooLow
+        );
+        Ok(())
+    }
+}
+
+impl GamblingLimits {
+    pub fn window_mut(&mut self, window: LimitWindow) -> &mut LimitWindowState {
+        match window {
+            LimitWindow::Daily => &mut self.daily,
+            LimitWindow::Weekly => &mut self.weekly,
+            LimitWindow::Monthly => &mut self.monthly,
+        }
+    }
+
+    // Starts a fresh period for any window that has run out and returns all of them
+    pub fn rolled(&mut self, now: i64) -> [&mut LimitWindowState; 3] {
+        self.daily.roll(LimitWindow::Daily.seconds(), now);
+        self.weekly.roll(LimitWindow::Weekly.seconds(), now);
+        self.monthly.roll(LimitWindow::Monthly.seconds(), now);
+        [&mut self.daily, &mut self.weekly, &mut self.monthly]
+    }
+}
+
+impl LimitWindowState {
+    pub fn limit_mut(&mut self, kind: LimitKind) -> &mut GamblingLimit {
+        match kind {
+            LimitKind::Deposit => &mut self.deposit_limit,
+            LimitKind::Wager => &mut self.wager_limit,
+            LimitKind::Loss => &mut self.loss_limit,
+        }
+    }
+
+    pub fn roll(&mut self, length: i64, now: i64) {
+        if now >= self.window_start.saturating_add(length) {
+            self.window_start = now;
+            self.deposited = 0;
+            self.wagered = 0;
+            self.returned = 0;
+        }
+    }
+
+    pub fn net_loss(&self) -> u64 {
+        self.wagered.saturating_sub(self.returned)
+    }
+}
+
+impl GamblingLimit {
+    // The limit in force at `now`, promoting a pending increase once its cooling-off has passed.
+    // An unset limit is unbounded.
+    pub fn current(&mut self, now: i64) -> u64 {
+        if let Some(pending) = self.pending_amount {
+            if now >= self.pending_effective_at {
+                self.amount = pending;
+                self.pending_amount = None;
+            }
+        }
+        if self.amount == 0 {
+            u64::MAX
+        } else {
+            self.amount
+        }
+    }
+
+    // Returns when the new limit takes effect
+    pub fn update(&mut self, amount: u64, now: i64) -> Result<i64> {
+        let requested = if amount == 0 { u64::MAX } else { amount };
+        if requested <= self.current(now) {
+            // Tightening applies at once and drops any increase still cooling off
+            self.amount = amount;
+            self.pending_amount = None;
+            return Ok(now);
+        }
+
+        let effective_at = math::add_seconds(now, LIMIT_INCREASE_COOLING_OFF)?;
+        self.pending_amount = Some(amount);
+        self.pending_effective_at = effective_at;
+        Ok(effective_at)
+    }
+}
+
+impl Investment {
+    pub fn is_closable(&self) -> bool {
+        self.status != InvestmentStatus::Active
+    }
+
+    // Re-derives the investment PDA for accounts passed through `remaining_accounts`
+    pub fn verify_address(&self, key: &Pubkey, program_id: &Pubkey) -> Result<()> {
+        let expected = Pubkey::create_program_address(
+            &[
+                b"investment",
+                self.user.as_ref(),
+                self.etf.as_ref(),
+                &[self.bump],
+            ],
+            program_id,
+        )
+        .map_err(|_| error!(SportPredictError::InvalidInvestmentAccount))?;
+        require_keys_eq!(expected, *key, SportPredictError::InvalidInvestmentAccount);
+        Ok(())
+    }
+}
+
+impl RoleRegistry {
+    pub fn has_role(&self, holder: &Pubkey, role: Role) -> bool {
+        self.grants
+            .iter()
+            .any(|grant| grant.holder == *holder && grant.role == role)
+    }
+
+    pub fn count(&self, role: Role) -> usize {
+        self.grants
+            .iter()
+            .filter(|grant| grant.role == role)
+            .count()
+    }
+
+    pub fn grant(&mut self, holder: Pubkey, role: Role) -> Result<()> {
+        require!(
+            !self.has_role(&holder, role),
+            SportPredictError::RoleAlreadyGranted
+        );
+        require!(
+            self.grants.len() < MAX_ROLE_GRANTS,
+            SportPredictError::TooManyRoleGrants
+        );
+        self.grants.push(RoleGrant { holder, role });
+        Ok(())
+    }
+
+    pub fn revoke(&mut self, holder: Pubkey, role: Role) -> Result<()> {
+        let position = self
+            .grants
+            .iter()
+            .position(|grant| grant.holder == holder && grant.role == role)
+            .ok_or(SportPredictError::RoleNotGranted)?;
+        self.grants.remove(position);
+
+        require!(self.count(Role::Admin) > 0, SportPredictError::LastAdmin);
+        // A compromised oracle must be removable at once, so the quorum shrinks with the oracle set.
+        // Once the last oracle is gone the quorum is zero and submit_result refuses every vote.
+        let oracles = self.count(Role::Oracle) as u8;
+        if self.oracle_quorum > oracles {
+            self.oracle_quorum = oracles;
+        }
+        Ok(())
+    }
+
+    // M of the N keys holding the oracle role must agree on a result
+    pub fn set_oracle_quorum(&mut self, quorum: u8) -> Result<()> {
+        require!(
+            quorum > 0 && quorum as usize <= self.count(Role::Oracle),
+            SportPredictError::InvalidQuorum
+        );
+        self.oracle_quorum = quorum;
+        Ok(())
+    }
+}
+
+impl ParamChange {
+    // Role allowed to schedule and cancel the change
+    pub fn required_role(&self) -> Role {
+        match self {
+            ParamChange::CashOutConfig { .. } | ParamChange::ExposureLimits { .. } => {
+                Role::RiskManager
+            }
+            ParamChange::MinKycLevel { .. } => Role::ComplianceOfficer,
+            _ => Role::Admin,
+        }
+    }
+
+    // Checks that don't depend on state, so a bad change is rejected when it is scheduled
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            ParamChange::FeeConfig {
+                winnings_fee_bps,
+                deposit_fee_bps,
+                withdrawal_fee_bps,
+            } => {
+                math::validate_bps(*winnings_fee_bps)?;
+                math::validate_bps(*deposit_fee_bps)?;
+                math::validate_bps(*withdrawal_fee_bps)?;
+            }
+            ParamChange::DisputeConfig {
+                dispute_period,
+                challenge_bond,
+            } => {
+                require!(
+                    *dispute_period >= 0,
+                    SportPredictError::InvalidDisputePeriod
+                );
+                require!(*challenge_bond > 0, SportPredictError::InvalidChallengeBond);
+            }
+            ParamChange::CancellationConfig {
+                bet_cancel_cutoff,
+                cancellation_fee_bps,
+            } => {
+                require!(
+                    *bet_cancel_cutoff >= 0,
+                    SportPredictError::InvalidCancelCutoff
+                );
+                math::validate_bps(*cancellation_fee_bps)?;
+            }
+            ParamChange::CashOutConfig {
+                cash_out_margin_bps,
+                max_odds_age,
+            } => {
+                math::validate_bps(*cash_out_margin_bps)?;
+                require!(*max_odds_age > 0, SportPredictError::InvalidOddsAge);
+            }
+            ParamChange::OracleQuorum { quorum } => {
+                require!(*quorum > 0, SportPredictError::InvalidQuorum);
+            }
+            ParamChange::TimelockDelay { delay } => {
+                require!(
+                    *delay >= MIN_TIMELOCK_DELAY,
+                    SportPredictError::InvalidTimelockDelay
+                );
+            }
+            ParamChange::ExposureLimits { .. }
+            | ParamChange::GrantRole { .. }
+            | ParamChange::MinKycLevel { .. } => {}
+        }
+        Ok(())
+    }
+
+    pub fn apply(&self, platform: &mut Platform, roles: &mut RoleRegistry) -> Result<()> {
+        match self.clone() {
+            ParamChange::FeeConfig {
+                winnings_fee_bps,
+                deposit_fee_bps,
+                withdrawal_fee_bps,
+            } => {
+                platform.winnings_fee_bps = winnings_fee_bps;
+                platform.deposit_fee_bps = deposit_fee_bps;
+                platform.withdrawal_fee_bps = withdrawal_fee_bps;
+            }
+            ParamChange::DisputeConfig {
+                dispute_period,
+                challenge_bond,
+            } => {
+                platform.dispute_period = dispute_period;
+                platform.challenge_bond = challenge_bond;
+            }
+            ParamChange::CancellationConfig {
+                bet_cancel_cutoff,
+                cancellation_fee_bps,
+            } => {
+                platform.bet_cancel_cutoff = bet_cancel_cutoff;
+                platform.cancellation_fee_bps = cancellation_fee_bps;
+            }
+            ParamChange::CashOutConfig {
+                cash_out_margin_bps,
+                max_odds_age,
+            } => {
+                platform.cash_out_margin_bps = cash_out_margin_bps;
+                platform.max_odds_age = max_odds_age;
+            }
+            ParamChange::ExposureLimits {
+                max_market_exposure,
+                max_platform_exposure,
+            } => {
+                platform.max_market_exposure = max_market_exposure;
+                platform.max_platform_exposure = max_platform_exposure;
+            }
+            ParamChange::GrantRole { holder, role } => roles.grant(holder, role)?,
+            ParamChange::OracleQuorum { quorum } => roles.set_oracle_quorum(quorum)?,
+            ParamChange::TimelockDelay { delay } => platform.timelock_delay = delay,
+            ParamChange::MinKycLevel { level } => platform.min_kyc_level = level,
+        }
+        Ok(())
+    }
+}
+
+impl FeeLedger {
+    pub fn accrue(&mut self, fee: u64) -> Result<()> {
+        self.accrued_fees = math::add(self.accrued_fees, fee)?;
+        self.total_collected = math::add(self.total_collected, fee)?;
+        Ok(())
+    }
+}
+
+impl Platform {
+    pub fn require_not_paused(&self, flag: u8) -> Result<()> {
+        if self.pause_flags & flag == 0 {
+            return Ok(());
+        }
+        match flag {
+            PAUSE_DEPOSITS => err!(SportPredictError::DepositsPaused),
+            PAUSE_WITHDRAWALS => err!(SportPredictError::WithdrawalsPaused),
+            PAUSE_BETTING => err!(SportPredictError::BettingPaused),
+            PAUSE_SETTLEMENT => err!(SportPredictError::SettlementPaused),
+            _ => err!(SportPredictError::EtfPaused),
+        }
+    }
+
+    pub fn update_exposure(&mut self, old: u64, new: u64) -> Result<()> {
+        self.total_exposure = math::add(math::sub(self.total_exposure, old)?, new)?;
+        Ok(())
+    }
+
+    // Exposure may only grow while it stays within the platform limit and the house reserve
+    pub fn check_exposure(&self) -> Result<()> {
+        require!(
+            self.total_exposure <= self.max_platform_exposure,
+            SportPredictError::PlatformExposureExceeded
+        );
+        require!(
+            self.total_exposure <= self.house_reserve,
+            SportPredictError::InsufficientHouseReserve
+        );
+        Ok(())
+    }
+
+    pub fn credit_house(&mut self, amount: u64) -> Result<()> {
+        self.house_reserve = math::add(self.house_reserve, amount)?;
+        Ok(())
+    }
+
+    pub fn debit_house(&mut self, amount: u64) -> Result<()> {
+        self.house_reserve = self
+            .house_reserve
+            .checked_sub(amount)
+            .ok_or(SportPredictError::InsufficientHouseReserve)?;
+        Ok(())
+    }
+
+    // What LP shares are worth: the house reserve less the worst case still riding on open bets
+    pub fn lp_pool_value(&self) -> u64 {
+        self.house_reserve.saturating_sub(self.total_exposure)
+    }
+}
+
+impl Market {
+    // Votes per outcome from keys that still hold the oracle role, and the number of current
+    // oracles that have yet to vote
+    pub fn tally(&self, roles: &RoleRegistry) -> (Vec<usize>, usize) {
+        let mut votes = vec![0; self.outcomes.len()];
+        for ballot in &self.ballots {
+            if roles.has_role(&ballot.oracle, Role::Oracle) {
+                votes[ballot.outcome_index as usize] += 1;
+            }
+        }
+        let outstanding = roles
+            .grants
+            .iter()
+            .filter(|grant| grant.role == Role::Oracle)
+            .filter(|grant| {
+                !self
+                    .ballots
+                    .iter()
+                    .any(|ballot| ballot.oracle == grant.holder)
+            })
+            .count();
+        (votes, outstanding)
+    }
+
+    // Settle a pending bet against the resolved outcome and return the fee taken. Losing stakes were
+    // credited to the house at resolution and winners' profit set aside; winners are paid from that.
+    pub fn settle_bet(
+        &mut self,
+        bet: &mut Bet,
+        user: &mut User,
+        platform: &mut Platform,
+        fee_ledger: &mut FeeLedger,
+        now: i64,
+    ) -> Result<u64> {
+        let winning_outcome = self
+            .winning_outcome
+            .ok_or(SportPredictError::MarketNotResolved)?;
+
+        bet.status = BetStatus::Settled;
+        bet.settled_at = Some(now);
+
+        if bet.outcome_index != winning_outcome {
+            bet.result = Some(BetResult::Loss);
+            bet.winnings = Some(0);
+            return Ok(0);
+        }
+
+        let profit = math::sub(bet.potential_winnings, bet.amount)?;
+        self.exposure = math::sub(self.exposure, profit)?;
+        platform.winnings_payable = math::sub(platform.winnings_payable, profit)?;
+
+        // The platform's fee is taken from the profit, never the returned stake
+        let fee = math::fee(profit, platform.winnings_fee_bps)?;
+        fee_ledger.accrue(fee)?;
+        let winnings = math::sub(bet.potential_winnings, fee)?;
+        user.balance = math::add(user.balance, winnings)?;
+        user.record_return(winnings, now)?;
+        user.total_wins += 1;
+        bet.result = Some(BetResult::Win);
+        bet.winnings = Some(winnings);
+
+        Ok(fee)
+    }
+
+    pub fn add_position(&mut self, outcome_index: u8, stake: u64, payout: u64) -> Result<()> {
+        let outcome = &mut self.outcomes[outcome_index as usize];
+        outcome.stake = math::add(outcome.stake, stake)?;
+        outcome.liability = math::add(outcome.liability, payout)?;
+        self.total_stake = math::add(self.total_stake, stake)?;
+        self.exposure = self.worst_case_exposure();
+        Ok(())
+    }
+
+    pub fn remove_position(&mut self, outcome_index: u8, stake: u64, payout: u64) -> Result<()> {
+        let outcome = &mut self.outcomes[outcome_index as usize];
+        outcome.stake = math::sub(outcome.stake, stake)?;
+        outcome.liability = math::sub(outcome.liability, payout)?;
+        self.total_stake = math::sub(self.total_stake, stake)?;
+        self.exposure = self.worst_case_exposure();
+        Ok(())
+    }
+
+    // The largest payout on any outcome less every stake taken on the market
+    pub fn worst_case_exposure(&self) -> u64 {
+        let max_liability = self
+            .outcomes
+            .iter()
+            .map(|outcome| outcome.liability)
+            .max()
+            .unwrap_or(0);
+        max_liability.saturating_sub(self.total_stake)
+    }
+
+    // Finalize the result. Losing stakes move to the house, and the profit owed to winners moves out of
+    // it so LPs can't redeem against money that is already spoken for.
+    pub fn resolve(
+        &mut self,
+        platform: &mut Platform,
+        winning_outcome: u8,
+        now: i64,
+    ) -> Result<()> {
+        let winner = &self.outcomes[winning_outcome as usize];
+        let owed = math::sub(winner.liability, winner.stake)?;
+        platform.credit_house(math::sub(self.total_stake, winner.stake)?)?;
+        platform.debit_house(owed)?;
+        platform.winnings_payable = math::add(platform.winnings_payable, owed)?;
+        platform.update_exposure(self.exposure, 0)?;
+
+        self.exposure = owed;
+        self.status = MarketStatus::Resolved;
+        self.winning_outcome = Some(winning_outcome);
+        self.resolved_at = Some(now);
+        Ok(())
+    }
+
+    // Re-derives the market PDA for accounts passed through `remaining_accounts`
+    pub fn verify_address(&self, key: &Pubkey, program_id: &Pubkey) -> Result<()> {
+        let expected = Pubkey::create_program_address(
+            &[
+                b"market",
+                self.match_account.as_ref(),
+                &[self.index],
+                &[self.bump],
+            ],
+            program_id,
+        )
+        .map_err(|_| error!(SportPredictError::MarketMismatch))?;
+        require_keys_eq!(expected, *key, SportPredictError::MarketMismatch);
+        Ok(())
+    }
+}
+
+impl Parlay {
+    // Profit the house would pay if every remaining leg wins
+    pub fn exposure(&self) -> Result<u64> {
+        math::sub(self.potential_winnings, self.amount)
+    }
+
+    // Settled parlays have been paid out and owe nothing further
+    pub fn is_closable(&self) -> bool {
+        self.status == BetStatus::Settled
+    }
+
+    // Re-derives the parlay PDA for accounts passed through `remaining_accounts`
+    pub fn verify_address(&self, key: &Pubkey, program_id: &Pubkey) -> Result<()> {
+        let expected = Pubkey::create_program_address(
+            &[
+                b"parlay",
+                self.user.as_ref(),
+                &self.nonce.to_le_bytes(),
+                &[self.bump],
+            ],
+            program_id,
+        )
+        .map_err(|_| error!(SportPredictError::InvalidParlayAccount))?;
+        require_keys_eq!(expected, *key, SportPredictError::InvalidParlayAccount);
+        Ok(())
+    }
+}
+
+impl Bet {
+    // Nothing is owed on a bet once it leaves the pending state
+    pub fn is_closable(&self) -> bool {
+        self.status != BetStatus::Pending
+    }
+
+    // Re-derives the bet PDA for accounts passed through `remaining_accounts`
+    pub fn verify_address(&self, key: &Pubkey, program_id: &Pubkey) -> Result<()> {
+        let expected = Pubkey::create_program_address(
+            &[
+                b"bet",
+                self.user.as_ref(),
+                &self.nonce.to_le_bytes(),
+                &[self.bump],
+            ],
+            program_id,
+        )
+        .map_err(|_| error!(SportPredictError::InvalidBetAccount))?;
+        require_keys_eq!(expected, *key, SportPredictError::InvalidBetAccount);
+        Ok(())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum Role {
+    Admin,
+    Oracle,
+    OddsSetter,
+    RiskManager,
+    ComplianceOfficer,
+    EtfCurator,
+    Arbitrator,
+}
+
+// A parameter update queued behind the platform timelock
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum ParamChange {
+    // Fees on winnings (charged on profit), deposits and withdrawals
+    FeeConfig {
+        winnings_fee_bps: u16,
+        deposit_fee_bps: u16,
+        withdrawal_fee_bps: u16,
+    },
+    // How long proposed results can be challenged and the bond required to do so
+    DisputeConfig {
+        dispute_period: i64,
+        challenge_bond: u64,
+    },
+    // How close to kickoff bettors may still cancel, and the fee kept on cancellation
+    CancellationConfig {
+        bet_cancel_cutoff: i64,
+        cancellation_fee_bps: u16,
+    },
+    // Margin the platform keeps when quoting cash-outs, and how old in-play odds may be
+    CashOutConfig {
+        cash_out_margin_bps: u16,
+        max_odds_age: i64,
+    },
+    // Worst-case loss the house will accept on a single market and across the platform
+    ExposureLimits {
+        max_market_exposure: u64,
+        max_platform_exposure: u64,
+    },
+    GrantRole {
+        holder: Pubkey,
+        role: Role,
+    },
+    OracleQuorum {
+        quorum: u8,
+    },
+    TimelockDelay {
+        delay: i64,
+    },
+    MinKycLevel {
+        level: u8,
+    },
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub struct RoleGrant {
+    pub holder: Pubkey,
+    pub role: Role,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum LimitKind {
+    Deposit,
+    Wager,
+    Loss,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum LimitWindow {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+impl LimitWindow {
+    pub fn seconds(&self) -> i64 {
+        match self {
+            LimitWindow::Daily => 24 * 60 * 60,
+            LimitWindow::Weekly => 7 * 24 * 60 * 60,
+            LimitWindow::Monthly => 30 * 24 * 60 * 60,
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, PartialEq, Eq, InitSpace)]
+pub struct GamblingLimit {
+    // Zero means no limit
+    pub amount: u64,
+    // A raised limit waiting out the cooling-off period
+    pub pending_amount: Option<u64>,
+    pub pending_effective_at: i64,
+}
+
+// Limits and usage for one fixed window, which restarts once its length has passed
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, PartialEq, Eq, InitSpace)]
+pub struct LimitWindowState {
+    pub deposit_limit: GamblingLimit,
+    pub wager_limit: GamblingLimit,
+    pub loss_limit: GamblingLimit,
+    pub window_start: i64,
+    pub deposited: u64,
+    pub wagered: u64,
+    // Winnings, refunds, cash-outs and redemptions; offsets wagers when measuring losses
+    pub returned: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, PartialEq, Eq, InitSpace)]
+pub struct GamblingLimits {
+    pub daily: LimitWindowState,
+    pub weekly: LimitWindowState,
+    pub monthly: LimitWindowState,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum MatchStatus {
+    Upcoming,
+    Live,
+    Finished,
+    Cancelled,
+}
+
+impl MatchStatus {
+    pub fn can_transition_to(&self, next: &MatchStatus) -> bool {
+        matches!(
+            (self, next),
+            (MatchStatus::Upcoming, MatchStatus::Live) | (MatchStatus::Live, MatchStatus::Finished)
+        )
+    }
+}
+
+// Mirrors the backend OddsType enum. Goal lines are expressed in hundredths of a goal (250 = 2.5).
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum MarketType {
+    MatchResult,
+    OverUnder { line: i32 },
+    BothTeamsScore,
+    CorrectScore,
+    // Line is applied to the home team's score
+    Handicap { line: i32 },
+}
+
+impl MarketType {
+    pub fn accepts(&self, outcome: &Outcome) -> bool {
+        matches!(
+            (self, outcome),
+            (
+                MarketType::MatchResult,
+                Outcome::HomeWin | Outcome::Draw | Outcome::AwayWin
+            ) | (MarketType::OverUnder { .. }, Outcome::Over | Outcome::Under)
+                | (MarketType::BothTeamsScore, Outcome::Yes | Outcome::No)
+                | (
+                    MarketType::CorrectScore,
+                    Outcome::Score { .. } | Outcome::OtherScore
+                )
+                | (
+                    MarketType::Handicap { .. },
+                    Outcome::HomeHandicap | Outcome::AwayHandicap
+                )
+        )
+    }
+
+    pub fn validate_outcomes(&self, outcomes: &[Outcome]) -> Result<()> {
+        // Whole-goal lines can land exactly on the line, which no outcome covers, so only half-goal
+        // lines are accepted
+        match self {
+            MarketType::OverUnder { line } => require!(
+                *line > 0 && line.rem_euclid(100) == 50,
+                SportPredictError::InvalidMarketLine
+            ),
+            MarketType::Handicap { line } => {
+                require!(
+                    line.rem_euclid(100) == 50,
+                    SportPredictError::InvalidMarketLine
+                )
+            }
+            _ => {}
+        }
+
+        // Fixed markets must list every outcome exactly once; correct score lists the scores on offer
+        // and must include the catch-all so every final score has a winning outcome
+        let expected = match self {
+            MarketType::MatchResult => Some(3),
+            MarketType::CorrectScore => None,
+            _ => Some(2),
+        };
+        match expected {
+            Some(count) => require!(outcomes.len() == count, SportPredictError::InvalidOutcome),
+            None => require!(
+                (2..=MAX_OUTCOMES).contains(&outcomes.len())
+                    && outcomes.contains(&Outcome::OtherScore),
+                SportPredictError::InvalidOutcome
+            ),
+        }
+
+        for (i, outcome) in outcomes.iter().enumerate() {
+            require!(self.accepts(outcome), SportPredictError::InvalidOutcome);
+            require!(
+                !outcomes[..i].contains(outcome),
+                SportPredictError::DuplicateOutcome
+            );
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum MarketStatus {
+    Open,
+    Proposed,
+    Disputed,
+    // Split oracle vote that can no longer reach quorum; awaiting the arbitrator
+    Deadlocked,
+    Resolved,
+    Voided,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum DisputeStatus {
+    Open,
+    Upheld,
+    Rejected,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub struct MarketOutcome {
+    pub outcome: Outcome,
+    // Decimal odds in 1e-4 units, 0 until published
+    pub odds: u32,
+    // Stakes taken and total payout owed (stake included) if this outcome wins
+    pub stake: u64,
+    pub liability: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub struct OracleBallot {
+    pub oracle: Pubkey,
+    pub outcome_index: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum Outcome {
+    HomeWin,
+    Draw,
+    AwayWin,
+    Over,
+    Under,
+    Yes,
+    No,
+    Score { home: u8, away: u8 },
+    HomeHandicap,
+    AwayHandicap,
+    // Any final score not listed on a correct score market
+    OtherScore,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub struct ParlayLeg {
+    pub match_account: Pubkey,
+    pub market: Pubkey,
+    pub outcome_index: u8,
+    pub odds: u32,
+    pub status: LegStatus,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum LegStatus {
+    Pending,
+    Won,
+    Lost,
+    Void,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
 pub enum BetStatus {
     Pending,
     Settled,
+    Cancelled,
+    CashedOut,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
 pub enum BetResult {
     Win,
     Loss,
-    Draw,
+    Void,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
 pub enum ETFStatus {
     Active,
     Paused,
     Closed,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
 pub enum InvestmentStatus {
     Active,
     Redeemed,
@@ -635,13 +4597,324 @@ pub enum SportPredictError {
     InsufficientShares,
     #[msg("Investment is inactive")]
     InvestmentInactive,
+    #[msg("Unauthorized")]
+    Unauthorized,
+    #[msg("Match id too long")]
+    MatchIdTooLong,
+    #[msg("Name too long")]
+    NameTooLong,
+    #[msg("Invalid match time")]
+    InvalidMatchTime,
+    #[msg("Invalid match status transition")]
+    InvalidMatchStatusTransition,
+    #[msg("Betting is closed for this match")]
+    BettingClosed,
+    #[msg("Bet does not belong to this match")]
+    MatchMismatch,
+    #[msg("Too many markets for this match")]
+    TooManyMarkets,
+    #[msg("Invalid outcome")]
+    InvalidOutcome,
+    #[msg("Duplicate outcome")]
+    DuplicateOutcome,
+    #[msg("Invalid market line")]
+    InvalidMarketLine,
+    #[msg("Arithmetic overflow")]
+    MathOverflow,
+    #[msg("Division by zero")]
+    DivisionByZero,
+    #[msg("Odds must be greater than 1.0")]
+    InvalidOdds,
+    #[msg("Fee exceeds 100%")]
+    InvalidFee,
+    #[msg("Odds have not been published for this outcome")]
+    OddsNotPublished,
+    #[msg("Odds changed since they were quoted")]
+    OddsChanged,
+    #[msg("Odds are below the requested minimum")]
+    OddsBelowMinimum,
+    #[msg("Match has not finished")]
+    MatchNotFinished,
+    #[msg("Market already resolved")]
+    MarketAlreadyResolved,
+    #[msg("Market has not been resolved")]
+    MarketNotResolved,
+    #[msg("Bet does not belong to this market")]
+    MarketMismatch,
+    #[msg("Quorum must be between 1 and the number of oracles")]
+    InvalidQuorum,
+    #[msg("Invalid dispute period")]
+    InvalidDisputePeriod,
+    #[msg("Challenge bond must be greater than zero")]
+    InvalidChallengeBond,
+    #[msg("Market has no proposed result")]
+    MarketNotProposed,
+    #[msg("Market is not disputed")]
+    MarketNotDisputed,
+    #[msg("Market is not deadlocked")]
+    MarketNotDeadlocked,
+    #[msg("Dispute period has ended")]
+    DisputePeriodEnded,
+    #[msg("Dispute period is still active")]
+    DisputePeriodActive,
+    #[msg("Match has not been cancelled")]
+    MatchNotCancelled,
+    #[msg("Remaining accounts must be (bet, user) pairs")]
+    InvalidRemainingAccounts,
+    #[msg("Invalid bet account")]
+    InvalidBetAccount,
+    #[msg("Invalid user account")]
+    InvalidUserAccount,
+    #[msg("Invalid cancellation cutoff")]
+    InvalidCancelCutoff,
+    #[msg("Bet can no longer be cancelled")]
+    CancellationClosed,
+    #[msg("Cash-out fraction must be between 1 and 10000 basis points")]
+    InvalidCashOutFraction,
+    #[msg("Cash-out is not available for this bet")]
+    CashOutUnavailable,
+    #[msg("Cash-out amount is below the requested minimum")]
+    CashOutBelowMinimum,
+    #[msg("Invalid number of parlay legs")]
+    InvalidParlayLegs,
+    #[msg("A parlay may only include one leg per match")]
+    DuplicateParlayMatch,
+    #[msg("Bet would exceed the market's exposure limit")]
+    MarketExposureExceeded,
+    #[msg("Bet would exceed the platform's exposure limit")]
+    PlatformExposureExceeded,
+    #[msg("House reserve cannot cover this liability")]
+    InsufficientHouseReserve,
+    #[msg("House pool has no value left to mint shares against")]
+    HousePoolInsolvent,
+    #[msg("Amount too small to mint LP shares")]
+    InvalidLpAmount,
+    #[msg("Liquidity is locked by open exposure")]
+    LiquidityLocked,
+    #[msg("Amount exceeds accrued platform fees")]
+    InsufficientAccruedFees,
+    #[msg("Role already granted")]
+    RoleAlreadyGranted,
+    #[msg("Role not granted")]
+    RoleNotGranted,
+    #[msg("Too many role grants")]
+    TooManyRoleGrants,
+    #[msg("Cannot revoke the last admin")]
+    LastAdmin,
+    #[msg("KYC attestation has expired")]
+    AttestationExpired,
+    #[msg("Age has not been verified")]
+    AgeNotVerified,
+    #[msg("KYC level is below the platform minimum")]
+    KycLevelTooLow,
+    #[msg("Region codes must be ISO 3166 country or subdivision codes")]
+    InvalidRegionCode,
+    #[msg("Too many region codes")]
+    TooManyRegionCodes,
+    #[msg("At least one region code is required")]
+    MissingRegionCode,
+    #[msg("Not available in the user's region")]
+    RegionRestricted,
+    #[msg("User is self-excluded")]
+    SelfExcluded,
+    #[msg("Exclusion must end in the future")]
+    InvalidExclusionPeriod,
+    #[msg("An exclusion cannot be shortened")]
+    ExclusionCannotBeShortened,
+    #[msg("Deposit limit exceeded")]
+    DepositLimitExceeded,
+    #[msg("Wager limit exceeded")]
+    WagerLimitExceeded,
+    #[msg("Loss limit exceeded")]
+    LossLimitExceeded,
+    #[msg("Username is too short")]
+    UsernameTooShort,
+    #[msg("Username is too long")]
+    UsernameTooLong,
+    #[msg("Usernames may only contain lowercase letters, digits and underscores")]
+    InvalidUsername,
+    #[msg("Bet is still pending")]
+    BetNotClosable,
+    #[msg("Investment has not been fully redeemed")]
+    InvestmentNotClosable,
+    #[msg("Investment account does not match its derived address")]
+    InvalidInvestmentAccount,
+    #[msg("Nonce does not match the next expected value")]
+    InvalidNonce,
+    #[msg("Timelock delay has not elapsed")]
+    TimelockNotElapsed,
+    #[msg("Timelock delay is below the minimum")]
+    InvalidTimelockDelay,
+    #[msg("Unknown pause flag")]
+    InvalidPauseFlags,
+    #[msg("Deposits are paused")]
+    DepositsPaused,
+    #[msg("Withdrawals are paused")]
+    WithdrawalsPaused,
+    #[msg("Betting is paused")]
+    BettingPaused,
+    #[msg("Settlement is paused")]
+    SettlementPaused,
+    #[msg("ETF operations are paused")]
+    EtfPaused,
+    #[msg("Maximum odds age must be positive")]
+    InvalidOddsAge,
+    #[msg("Odds are too old to quote a cash-out")]
+    StaleOdds,
+    #[msg("LP shares are still within the minimum hold period")]
+    LpHoldPeriod,
+    #[msg("Parlay is still pending")]
+    ParlayNotClosable,
+    #[msg("Parlay account does not match its derived address")]
+    InvalidParlayAccount,
 }
 
 // Events
+#[event]
+pub struct RoleUpdatedEvent {
+    pub holder: Pubkey,
+    pub role: Role,
+    pub granted: bool,
+    pub by: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct OracleQuorumLoweredEvent {
+    pub previous_quorum: u8,
+    pub quorum: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct GamblingLimitUpdatedEvent {
+    pub user: Pubkey,
+    pub kind: LimitKind,
+    pub window: LimitWindow,
+    pub amount: u64,
+    pub effective_at: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AttestationIssuedEvent {
+    pub wallet: Pubkey,
+    pub kyc_level: u8,
+    pub expires_at: i64,
+    pub age_verified: bool,
+    pub regions: Vec<[u8; 6]>,
+    pub issued_by: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct BlockedRegionsUpdatedEvent {
+    // Platform (new-account default), market or ETF
+    pub account: Pubkey,
+    pub blocked_regions: Vec<[u8; 6]>,
+    pub by: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AttestationRevokedEvent {
+    pub wallet: Pubkey,
+    pub revoked_by: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct UserSelfExcludedEvent {
+    pub user: Pubkey,
+    pub excluded_until: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct UserRenamedEvent {
+    pub user: Pubkey,
+    pub old_username: String,
+    pub new_username: String,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct UserStatusChangedEvent {
+    pub user: Pubkey,
+    pub is_active: bool,
+    pub by: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AuthorityProposedEvent {
+    pub authority: Pubkey,
+    pub pending_authority: Option<Pubkey>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AuthorityTransferredEvent {
+    pub previous: Pubkey,
+    pub authority: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ChangeScheduledEvent {
+    pub id: u64,
+    pub change: ParamChange,
+    pub proposer: Pubkey,
+    pub eta: i64,
+}
+
+#[event]
+pub struct ChangeExecutedEvent {
+    pub id: u64,
+    pub change: ParamChange,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ChangeCancelledEvent {
+    pub id: u64,
+    pub by: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct BetsClaimedEvent {
+    pub market: Pubkey,
+    pub won: u32,
+    pub lost: u32,
+    pub skipped: u32,
+    pub total_winnings: u64,
+    pub total_fees: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AccountsClosedEvent {
+    pub user: Pubkey,
+    pub bets_closed: u32,
+    pub parlays_closed: u32,
+    pub investments_closed: u32,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PauseFlagsUpdatedEvent {
+    pub previous: u8,
+    pub pause_flags: u8,
+    pub by: Pubkey,
+    pub timestamp: i64,
+}
+
 #[event]
 pub struct DepositEvent {
     pub user: Pubkey,
     pub amount: u64,
+    pub fee: u64,
     pub timestamp: i64,
 }
 
@@ -649,6 +4922,65 @@ pub struct DepositEvent {
 pub struct WithdrawEvent {
     pub user: Pubkey,
     pub amount: u64,
+    pub fee: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PlatformFeesWithdrawnEvent {
+    pub authority: Pubkey,
+    pub amount: u64,
+    pub remaining: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MatchCreatedEvent {
+    pub match_account: Pubkey,
+    pub match_id: String,
+    pub start_time: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MatchStatusUpdatedEvent {
+    pub match_account: Pubkey,
+    pub status: MatchStatus,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MarketCreatedEvent {
+    pub match_account: Pubkey,
+    pub market: Pubkey,
+    pub market_type: MarketType,
+    pub outcome_count: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct OddsUpdatedEvent {
+    pub market: Pubkey,
+    pub odds: Vec<u32>,
+    pub version: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct LpDepositEvent {
+    pub provider: Pubkey,
+    pub amount: u64,
+    pub shares: u64,
+    pub house_reserve: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct LpWithdrawEvent {
+    pub provider: Pubkey,
+    pub amount: u64,
+    pub shares: u64,
+    pub house_reserve: u64,
     pub timestamp: i64,
 }
 
@@ -656,9 +4988,73 @@ pub struct WithdrawEvent {
 pub struct BetPlacedEvent {
     pub user: Pubkey,
     pub bet: Pubkey,
+    pub match_account: Pubkey,
     pub match_id: String,
     pub amount: u64,
-    pub prediction: String,
+    pub market: Pubkey,
+    pub outcome_index: u8,
+    pub odds: u32,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct OracleVoteEvent {
+    pub market: Pubkey,
+    pub oracle: Pubkey,
+    pub outcome_index: u8,
+    pub votes: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ConflictingVoteEvent {
+    pub market: Pubkey,
+    pub oracle: Pubkey,
+    pub outcome_index: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ResultProposedEvent {
+    pub market: Pubkey,
+    pub outcome_index: u8,
+    pub votes: u8,
+    pub dispute_deadline: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct QuorumDeadlockedEvent {
+    pub market: Pubkey,
+    pub votes_cast: u8,
+    pub quorum: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ResultChallengedEvent {
+    pub market: Pubkey,
+    pub challenger: Pubkey,
+    pub proposed_outcome: u8,
+    pub challenged_outcome: u8,
+    pub bond: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct DisputeRuledEvent {
+    pub market: Pubkey,
+    pub dispute: Pubkey,
+    pub upheld: bool,
+    pub bond: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MarketResolvedEvent {
+    pub market: Pubkey,
+    pub winning_outcome: u8,
+    pub disputed: bool,
     pub timestamp: i64,
 }
 
@@ -667,6 +5063,7 @@ pub struct BetWonEvent {
     pub user: Pubkey,
     pub bet: Pubkey,
     pub winnings: u64,
+    pub fee: u64,
     pub timestamp: i64,
 }
 
@@ -678,10 +5075,58 @@ pub struct BetLostEvent {
 }
 
 #[event]
-pub struct BetDrawEvent {
+pub struct BetVoidedEvent {
+    pub user: Pubkey,
+    pub bet: Pubkey,
+    pub refund_amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct BetCancelledEvent {
     pub user: Pubkey,
     pub bet: Pubkey,
     pub refund_amount: u64,
+    pub fee: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct BetCashedOutEvent {
+    pub user: Pubkey,
+    pub bet: Pubkey,
+    pub stake: u64,
+    pub amount: u64,
+    pub remaining_stake: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MatchBetsRefundedEvent {
+    pub match_account: Pubkey,
+    pub refunded: u32,
+    pub skipped: u32,
+    pub total_refunded: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ParlayPlacedEvent {
+    pub user: Pubkey,
+    pub parlay: Pubkey,
+    pub legs: u8,
+    pub amount: u64,
+    pub combined_odds: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ParlaySettledEvent {
+    pub user: Pubkey,
+    pub parlay: Pubkey,
+    pub result: BetResult,
+    pub winnings: u64,
+    pub fee: u64,
     pub timestamp: i64,
 }
 
@@ -701,4 +5146,297 @@ pub struct ETFRedeemEvent {
     pub shares: u64,
     pub redemption_amount: u64,
     pub timestamp: i64,
-} 
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(home: u8, away: u8) -> Outcome {
+        Outcome::Score { home, away }
+    }
+
+    #[test]
+    fn match_result_requires_all_three_outcomes() {
+        let market = MarketType::MatchResult;
+        assert!(market
+            .validate_outcomes(&[Outcome::HomeWin, Outcome::Draw, Outcome::AwayWin])
+            .is_ok());
+        assert_eq!(
+            market.validate_outcomes(&[Outcome::HomeWin, Outcome::AwayWin]),
+            Err(SportPredictError::InvalidOutcome.into())
+        );
+        assert_eq!(
+            market.validate_outcomes(&[Outcome::HomeWin, Outcome::Draw, Outcome::Draw]),
+            Err(SportPredictError::DuplicateOutcome.into())
+        );
+        assert_eq!(
+            market.validate_outcomes(&[Outcome::HomeWin, Outcome::Draw, Outcome::Over]),
+            Err(SportPredictError::InvalidOutcome.into())
+        );
+    }
+
+    #[test]
+    fn goal_lines_must_be_half_goals() {
+        let outcomes = [Outcome::Over, Outcome::Under];
+        assert!(MarketType::OverUnder { line: 250 }
+            .validate_outcomes(&outcomes)
+            .is_ok());
+        for line in [0, -250, 200, 225] {
+            assert_eq!(
+                MarketType::OverUnder { line }.validate_outcomes(&outcomes),
+                Err(SportPredictError::InvalidMarketLine.into())
+            );
+        }
+
+        let outcomes = [Outcome::HomeHandicap, Outcome::AwayHandicap];
+        for line in [-150, -50, 50, 150] {
+            assert!(MarketType::Handicap { line }
+                .validate_outcomes(&outcomes)
+                .is_ok());
+        }
+        for line in [-100, 0, 100, 125] {
+            assert_eq!(
+                MarketType::Handicap { line }.validate_outcomes(&outcomes),
+                Err(SportPredictError::InvalidMarketLine.into())
+            );
+        }
+    }
+
+    #[test]
+    fn correct_score_requires_other_score() {
+        let market = MarketType::CorrectScore;
+        assert!(market
+            .validate_outcomes(&[score(1, 0), score(0, 0), Outcome::OtherScore])
+            .is_ok());
+        assert_eq!(
+            market.validate_outcomes(&[score(1, 0), score(0, 0)]),
+            Err(SportPredictError::InvalidOutcome.into())
+        );
+        assert_eq!(
+            market.validate_outcomes(&[Outcome::OtherScore]),
+            Err(SportPredictError::InvalidOutcome.into())
+        );
+        assert_eq!(
+            market.validate_outcomes(&[score(1, 0), score(1, 0), Outcome::OtherScore]),
+            Err(SportPredictError::DuplicateOutcome.into())
+        );
+    }
+
+    #[test]
+    fn revoking_an_oracle_lowers_the_quorum() {
+        let admin = Pubkey::new_unique();
+        let oracles = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let mut roles = RoleRegistry {
+            grants: vec![RoleGrant {
+                holder: admin,
+                role: Role::Admin,
+            }],
+            oracle_quorum: 0,
+            bump: 0,
+        };
+        for oracle in oracles {
+            roles.grant(oracle, Role::Oracle).unwrap();
+        }
+        roles.set_oracle_quorum(2).unwrap();
+
+        roles.revoke(oracles[0], Role::Oracle).unwrap();
+        assert_eq!(roles.oracle_quorum, 1);
+        roles.revoke(oracles[1], Role::Oracle).unwrap();
+        assert_eq!(roles.oracle_quorum, 0);
+
+        assert_eq!(
+            roles.revoke(admin, Role::Admin),
+            Err(SportPredictError::LastAdmin.into())
+        );
+    }
+
+    #[test]
+    fn revoked_oracles_drop_out_of_the_tally() {
+        let oracles = [
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        ];
+        let mut roles = RoleRegistry {
+            grants: vec![RoleGrant {
+                holder: Pubkey::new_unique(),
+                role: Role::Admin,
+            }],
+            oracle_quorum: 0,
+            bump: 0,
+        };
+        for oracle in oracles {
+            roles.grant(oracle, Role::Oracle).unwrap();
+        }
+        roles.set_oracle_quorum(2).unwrap();
+        let market = Market {
+            match_account: Pubkey::new_unique(),
+            index: 0,
+            market_type: MarketType::MatchResult,
+            outcomes: [Outcome::HomeWin, Outcome::Draw, Outcome::AwayWin]
+                .into_iter()
+                .map(|outcome| MarketOutcome {
+                    outcome,
+                    odds: 0,
+                    stake: 0,
+                    liability: 0,
+                })
+                .collect(),
+            ballots: vec![
+                OracleBallot {
+                    oracle: oracles[0],
+                    outcome_index: 0,
+                },
+                OracleBallot {
+                    oracle: oracles[1],
+                    outcome_index: 2,
+                },
+            ],
+            odds_version: 0,
+            odds_updated_at: 0,
+            total_stake: 0,
+            exposure: 0,
+            blocked_regions: Vec::new(),
+            status: MarketStatus::Open,
+            proposed_outcome: None,
+            proposed_at: None,
+            winning_outcome: None,
+            resolved_at: None,
+            bump: 0,
+            created_at: 0,
+        };
+        assert_eq!(market.tally(&roles), (vec![1, 0, 1], 1));
+
+        roles.revoke(oracles[0], Role::Oracle).unwrap();
+        assert_eq!(market.tally(&roles), (vec![0, 0, 1], 1));
+        roles.revoke(oracles[2], Role::Oracle).unwrap();
+        assert_eq!(market.tally(&roles), (vec![0, 0, 1], 0));
+    }
+
+    #[test]
+    fn lowering_a_limit_applies_at_once() {
+        let mut limit = GamblingLimit::default();
+        assert_eq!(limit.current(0), u64::MAX);
+
+        assert_eq!(limit.update(100, 0), Ok(0));
+        assert_eq!(limit.current(0), 100);
+        assert_eq!(limit.update(50, 10), Ok(10));
+        assert_eq!(limit.current(10), 50);
+    }
+
+    #[test]
+    fn raising_a_limit_waits_for_the_cooling_off() {
+        let mut limit = GamblingLimit::default();
+        limit.update(100, 0).unwrap();
+
+        let effective_at = limit.update(200, 1_000).unwrap();
+        assert_eq!(effective_at, 1_000 + LIMIT_INCREASE_COOLING_OFF);
+        assert_eq!(limit.current(effective_at - 1), 100);
+        assert_eq!(limit.current(effective_at), 200);
+        assert_eq!(limit.pending_amount, None);
+    }
+
+    #[test]
+    fn removing_a_limit_counts_as_raising_it() {
+        let mut limit = GamblingLimit::default();
+        limit.update(100, 0).unwrap();
+
+        let effective_at = limit.update(0, 0).unwrap();
+        assert_eq!(limit.current(0), 100);
+        assert_eq!(limit.current(effective_at), u64::MAX);
+    }
+
+    #[test]
+    fn lowering_a_limit_drops_a_pending_increase() {
+        let mut limit = GamblingLimit::default();
+        limit.update(100, 0).unwrap();
+        limit.update(200, 0).unwrap();
+
+        assert_eq!(limit.update(80, 10), Ok(10));
+        assert_eq!(limit.current(LIMIT_INCREASE_COOLING_OFF), 80);
+    }
+
+    fn attested_in(codes: &[&str]) -> Attestation {
+        let codes: Vec<String> = codes.iter().map(|code| code.to_string()).collect();
+        Attestation {
+            wallet: Pubkey::new_unique(),
+            kyc_level: 1,
+            expires_at: i64::MAX,
+            age_verified: true,
+            regions: Attestation::parse_regions(&codes, MAX_ATTESTED_REGIONS).unwrap(),
+            issued_by: Pubkey::new_unique(),
+            issued_at: 0,
+            bump: 0,
+        }
+    }
+
+    fn regions(codes: &[&str]) -> Vec<RegionCode> {
+        codes
+            .iter()
+            .map(|code| Attestation::parse_region(code).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn region_codes_must_be_iso_3166() {
+        assert_eq!(Attestation::parse_region("CN"), Ok(*b"CN\0\0\0\0"));
+        assert_eq!(Attestation::parse_region("IN-AP"), Ok(*b"IN-AP\0"));
+        assert_eq!(Attestation::parse_region("FR-75C"), Ok(*b"FR-75C"));
+        for code in [
+            "", "C", "cn", "CHN", "IN-", "IN_AP", "in-ap", "IN-ABCD", "12",
+        ] {
+            assert_eq!(
+                Attestation::parse_region(code),
+                Err(SportPredictError::InvalidRegionCode.into()),
+                "{code}"
+            );
+        }
+    }
+
+    #[test]
+    fn region_lists_are_bounded_and_deduplicated() {
+        let codes: Vec<String> = ["IN", "IN-KL", "IN"]
+            .iter()
+            .map(|code| code.to_string())
+            .collect();
+        assert_eq!(
+            Attestation::parse_regions(&codes, 3),
+            Ok(regions(&["IN", "IN-KL"]))
+        );
+        assert_eq!(
+            Attestation::parse_regions(&codes, 2),
+            Err(SportPredictError::TooManyRegionCodes.into())
+        );
+    }
+
+    #[test]
+    fn country_blocks_cover_subdivisions() {
+        let blocked = regions(&["CN", "IN-KL"]);
+        assert!(attested_in(&["CN"]).is_blocked_in(&blocked));
+        assert!(attested_in(&["CN-BJ"]).is_blocked_in(&blocked));
+        assert!(attested_in(&["IN", "IN-KL"]).is_blocked_in(&blocked));
+        // Without a subdivision the holder could be in the blocked state
+        assert!(attested_in(&["IN"]).is_blocked_in(&blocked));
+        assert!(!attested_in(&["IN", "IN-KA"]).is_blocked_in(&blocked));
+        assert!(!attested_in(&["GB"]).is_blocked_in(&blocked));
+        assert!(!attested_in(&["CN"]).is_blocked_in(&[]));
+    }
+
+    #[test]
+    fn default_blocked_regions_are_valid() {
+        assert!(DEFAULT_BLOCKED_REGIONS.len() <= MAX_BLOCKED_REGIONS);
+        for code in DEFAULT_BLOCKED_REGIONS {
+            assert!(Attestation::parse_region(code).is_ok(), "{code}");
+        }
+    }
+
+    // `#[max_len]` only takes literals, so the account fields spell these sizes out by hand
+    #[test]
+    fn size_constants_match_account_fields() {
+        assert_eq!(MAX_BLOCKED_REGIONS, 96);
+        assert_eq!(MAX_EXTRA_BLOCKED_REGIONS, 16);
+        assert_eq!(MAX_ATTESTED_REGIONS, 4);
+        assert_eq!(REGION_CODE_LEN, 6);
+    }
+}
@@ -0,0 +1,158 @@
+use anchor_lang::prelude::*;
+
+use crate::SportPredictError;
+
+// Decimal odds are stored in 1e-4 units, so 2.5 is 25_000
+pub const ODDS_SCALE: u64 = 10_000;
+// Fees, margins and returns are stored in basis points
+pub const BPS_SCALE: u64 = 10_000;
+
+// Odds of exactly 1.0 would return only the stake, so anything at or below that is rejected
+pub const MIN_ODDS: u32 = 10_001;
+
+pub fn add(a: u64, b: u64) -> Result<u64> {
+    a.checked_add(b)
+        .ok_or_else(|| error!(SportPredictError::MathOverflow))
+}
+
+pub fn sub(a: u64, b: u64) -> Result<u64> {
+    a.checked_sub(b)
+        .ok_or_else(|| error!(SportPredictError::MathOverflow))
+}
+
+// a * b / c, rounded down
+pub fn mul_div_floor(a: u64, b: u64, c: u64) -> Result<u64> {
+    require!(c != 0, SportPredictError::DivisionByZero);
+    let result = (a as u128) * (b as u128) / (c as u128);
+    u64::try_from(result).map_err(|_| error!(SportPredictError::MathOverflow))
+}
+
+// a * b / c, rounded up
+pub fn mul_div_ceil(a: u64, b: u64, c: u64) -> Result<u64> {
+    require!(c != 0, SportPredictError::DivisionByZero);
+    // Spelled out rather than u128::div_ceil, which the SBF toolchain's rustc predates
+    let product = (a as u128) * (b as u128);
+    let c = c as u128;
+    let quotient = product / c;
+    let result = if quotient * c < product {
+        quotient + 1
+    } else {
+        quotient
+    };
+    u64::try_from(result).map_err(|_| error!(SportPredictError::MathOverflow))
+}
+
+pub fn add_seconds(timestamp: i64, seconds: i64) -> Result<i64> {
+    timestamp
+        .checked_add(seconds)
+        .ok_or_else(|| error!(SportPredictError::MathOverflow))
+}
+
+pub fn validate_odds(odds: u32) -> Result<()> {
+    require!(odds >= MIN_ODDS, SportPredictError::InvalidOdds);
+    Ok(())
+}
+
+pub fn validate_bps(bps: u16) -> Result<()> {
+    require!(bps as u64 <= BPS_SCALE, SportPredictError::InvalidFee);
+    Ok(())
+}
+
+// Total returned to a winning bet (stake included). Rounded down in the house's favour.
+// Takes u64 odds so combined parlay odds can exceed the u32 range of a single price.
+pub fn payout(stake: u64, odds: u64) -> Result<u64> {
+    mul_div_floor(stake, odds, ODDS_SCALE)
+}
+
+// Multiplies accumulated odds by one more leg's price. Rounded down in the house's favour.
+pub fn combine_odds(combined: u64, odds: u32) -> Result<u64> {
+    mul_div_floor(combined, odds as u64, ODDS_SCALE)
+}
+
+// Fee charged on an amount. Rounded up in the house's favour.
+pub fn fee(amount: u64, bps: u16) -> Result<u64> {
+    mul_div_ceil(amount, bps as u64, BPS_SCALE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_and_sub_reject_overflow() {
+        assert_eq!(add(1, 2), Ok(3));
+        assert_eq!(
+            add(u64::MAX, 1),
+            Err(SportPredictError::MathOverflow.into())
+        );
+        assert_eq!(sub(3, 2), Ok(1));
+        assert_eq!(sub(2, 3), Err(SportPredictError::MathOverflow.into()));
+        assert_eq!(
+            add_seconds(i64::MAX, 1),
+            Err(SportPredictError::MathOverflow.into())
+        );
+    }
+
+    #[test]
+    fn mul_div_rounds_in_the_named_direction() {
+        assert_eq!(mul_div_floor(10, 1, 3), Ok(3));
+        assert_eq!(mul_div_ceil(10, 1, 3), Ok(4));
+        assert_eq!(mul_div_floor(9, 1, 3), Ok(3));
+        assert_eq!(mul_div_ceil(9, 1, 3), Ok(3));
+        assert_eq!(mul_div_ceil(0, 7, 3), Ok(0));
+    }
+
+    #[test]
+    fn mul_div_rejects_zero_divisor() {
+        assert_eq!(
+            mul_div_floor(1, 1, 0),
+            Err(SportPredictError::DivisionByZero.into())
+        );
+        assert_eq!(
+            mul_div_ceil(1, 1, 0),
+            Err(SportPredictError::DivisionByZero.into())
+        );
+    }
+
+    #[test]
+    fn mul_div_uses_a_wide_intermediate() {
+        // The product overflows u64 but the quotient fits
+        assert_eq!(mul_div_floor(u64::MAX, 4, 4), Ok(u64::MAX));
+        assert_eq!(mul_div_ceil(u64::MAX, u64::MAX, u64::MAX), Ok(u64::MAX));
+        assert_eq!(
+            mul_div_floor(u64::MAX, 2, 1),
+            Err(SportPredictError::MathOverflow.into())
+        );
+        assert_eq!(
+            mul_div_ceil(u64::MAX, 2, 1),
+            Err(SportPredictError::MathOverflow.into())
+        );
+    }
+
+    #[test]
+    fn payout_and_fee_favour_the_house() {
+        // 3 units at 1.3333 returns 3.9999, rounded down
+        assert_eq!(payout(3, 13_333), Ok(3));
+        assert_eq!(payout(1_000_000, 25_000), Ok(2_500_000));
+        assert_eq!(combine_odds(20_000, 15_001), Ok(30_002));
+        assert_eq!(combine_odds(15_001, 15_001), Ok(22_503));
+        // 1% of 150 is 1.5, rounded up
+        assert_eq!(fee(150, 100), Ok(2));
+        assert_eq!(fee(200, 100), Ok(2));
+        assert_eq!(fee(0, 100), Ok(0));
+    }
+
+    #[test]
+    fn validation_bounds() {
+        assert!(validate_odds(MIN_ODDS).is_ok());
+        assert_eq!(
+            validate_odds(10_000),
+            Err(SportPredictError::InvalidOdds.into())
+        );
+        assert!(validate_bps(10_000).is_ok());
+        assert_eq!(
+            validate_bps(10_001),
+            Err(SportPredictError::InvalidFee.into())
+        );
+    }
+}
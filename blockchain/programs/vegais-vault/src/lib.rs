@@ -14,6 +14,7 @@ pub mod vegais_vault {
     ) -> Result<()> {
         let vault = &mut ctx.accounts.vault;
         vault.authority = ctx.accounts.authority.key();
+        vault.pending_authority = None;
         vault.name = vault_name;
         vault.symbol = vault_symbol;
         vault.total_deposits = 0;
@@ -200,6 +201,29 @@ pub mod vegais_vault {
         msg!("Fees collected: {} tokens", amount);
         Ok(())
     }
+
+    pub fn propose_authority(
+        ctx: Context<ProposeAuthority>,
+        new_authority: Option<Pubkey>,
+    ) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+
+        // The transfer only completes once the new authority accepts
+        vault.pending_authority = new_authority;
+
+        msg!("Vault authority proposed: {:?}", new_authority);
+        Ok(())
+    }
+
+    pub fn accept_authority(ctx: Context<AcceptAuthority>) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+
+        vault.authority = ctx.accounts.new_authority.key();
+        vault.pending_authority = None;
+
+        msg!("Vault authority transferred to {}", vault.authority);
+        Ok(())
+    }
 }
 
 #[derive(Accounts)]
@@ -339,9 +363,36 @@ pub struct CollectFees<'info> {
     pub token_program: Program<'info, Token>,
 }
 
+#[derive(Accounts)]
+pub struct ProposeAuthority<'info> {
+    #[account(
+        mut,
+        seeds = [b"vault"],
+        bump = vault.bump,
+        has_one = authority @ VaultError::Unauthorized
+    )]
+    pub vault: Account<'info, Vault>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptAuthority<'info> {
+    #[account(
+        mut,
+        seeds = [b"vault"],
+        bump = vault.bump,
+        constraint = vault.pending_authority == Some(new_authority.key()) @ VaultError::Unauthorized
+    )]
+    pub vault: Account<'info, Vault>,
+    
+    pub new_authority: Signer<'info>,
+}
+
 #[account]
 pub struct Vault {
     pub authority: Pubkey,
+    pub pending_authority: Option<Pubkey>,
     pub name: String,
     pub symbol: String,
     pub total_deposits: u64,
@@ -392,7 +443,7 @@ pub enum BetStatus {
 }
 
 impl Vault {
-    pub const LEN: usize = 8 + 32 + 4 + 50 + 4 + 10 + 8 + 8 + 8 + 8 + 1 + 1;
+    pub const LEN: usize = 8 + 32 + 33 + 4 + 50 + 4 + 10 + 8 + 8 + 8 + 8 + 1 + 1;
 }
 
 impl UserAccount {
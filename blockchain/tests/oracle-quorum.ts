@@ -0,0 +1,188 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { Keypair, LAMPORTS_PER_SOL, PublicKey, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import { SportPredict } from "../target/types/sport_predict";
+
+describe("oracle quorum", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace.SportPredict as Program<SportPredict>;
+  const authority = provider.wallet.publicKey;
+
+  // Local keypairs stand in for the oracle network
+  const oracles = [Keypair.generate(), Keypair.generate(), Keypair.generate()];
+  const outsider = Keypair.generate();
+  const matchId = `quorum-${Date.now()}`;
+
+  const pda = (...seeds: (Buffer | Uint8Array)[]) =>
+    PublicKey.findProgramAddressSync(seeds, program.programId)[0];
+
+  const platform = pda(Buffer.from("platform"));
+  const feeLedger = pda(Buffer.from("fee_ledger"));
+  const roles = pda(Buffer.from("roles"));
+  const matchAccount = pda(Buffer.from("match"), Buffer.from(matchId));
+  const market = pda(Buffer.from("market"), matchAccount.toBuffer(), Buffer.from([0]));
+  // Second market on the same match for the split-vote case
+  const splitMarket = pda(Buffer.from("market"), matchAccount.toBuffer(), Buffer.from([1]));
+  const voteOf = (oracle: PublicKey, target = market) =>
+    pda(Buffer.from("oracle_vote"), target.toBuffer(), oracle.toBuffer());
+
+  const eventsOf = async (signature: string) => {
+    const tx = await provider.connection.getTransaction(signature, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const parser = new anchor.EventParser(program.programId, new anchor.BorshCoder(program.idl));
+    return Array.from(parser.parseLogs(tx.meta.logMessages));
+  };
+
+  // Role grants and the quorum go through the timelock; the default delay of zero lets them run at once
+  const applyChange = async (change: any) => {
+    const { changeCount } = await program.account.platform.fetch(platform);
+    const pendingChange = pda(Buffer.from("pending_change"), changeCount.toArrayLike(Buffer, "le", 8));
+
+    await program.methods
+      .scheduleChange(change)
+      .accounts({ pendingChange, platform, roles, proposer: authority, systemProgram: SystemProgram.programId })
+      .rpc();
+    await program.methods
+      .executeChange()
+      .accounts({ pendingChange, platform, roles, proposer: authority, executor: authority })
+      .rpc();
+  };
+
+  const submit = (oracle: Keypair, outcomeIndex: number, target = market) =>
+    program.methods
+      .submitResult(outcomeIndex)
+      .accounts({
+        matchAccount,
+        market: target,
+        oracleVote: voteOf(oracle.publicKey, target),
+        roles,
+        platform,
+        oracle: oracle.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([oracle])
+      .rpc({ commitment: "confirmed" });
+
+  before(async () => {
+    for (const kp of [...oracles, outsider]) {
+      const sig = await provider.connection.requestAirdrop(kp.publicKey, LAMPORTS_PER_SOL);
+      await provider.connection.confirmTransaction(sig, "confirmed");
+    }
+
+    if ((await provider.connection.getAccountInfo(platform)) === null) {
+      // No timelock delay so the suite can apply its own setup changes at once
+      await program.methods
+        .initialize(new anchor.BN(0))
+        .accounts({ platform, feeLedger, roles, authority, systemProgram: SystemProgram.programId })
+        .rpc();
+    }
+
+    // Markets are created by an odds setter; the authority only starts out as admin
+    await applyChange({ grantRole: { holder: authority, role: { oddsSetter: {} } } });
+
+    const startTime = new anchor.BN(Math.floor(Date.now() / 1000) + 3600);
+    await program.methods
+      .createMatch(matchId, "Home FC", "Away FC", "Test League", "football", startTime)
+      .accounts({ matchAccount, roles, authority, systemProgram: SystemProgram.programId })
+      .rpc();
+
+    for (const target of [market, splitMarket]) {
+      await program.methods
+        .createMarket({ matchResult: {} }, [{ homeWin: {} }, { draw: {} }, { awayWin: {} }])
+        .accounts({ matchAccount, market: target, roles, authority, systemProgram: SystemProgram.programId })
+        .rpc();
+    }
+
+    for (const status of [{ live: {} }, { finished: {} }]) {
+      await program.methods
+        .updateMatchStatus(status)
+        .accounts({ matchAccount, roles, authority })
+        .rpc();
+    }
+
+    for (const kp of oracles) {
+      await applyChange({ grantRole: { holder: kp.publicKey, role: { oracle: {} } } });
+    }
+    await applyChange({ oracleQuorum: { quorum: 2 } });
+  });
+
+  it("rejects results from keys outside the oracle set", async () => {
+    try {
+      await submit(outsider, 0);
+      assert.fail("outsider vote should be rejected");
+    } catch (err) {
+      assert.include(String(err), "Unauthorized");
+    }
+  });
+
+  it("does not resolve below quorum", async () => {
+    await submit(oracles[0], 0);
+
+    const state = await program.account.market.fetch(market);
+    assert.deepEqual(state.status, { open: {} });
+    assert.isNull(state.winningOutcome);
+
+    const vote = await program.account.oracleVote.fetch(voteOf(oracles[0].publicKey));
+    assert.equal(vote.outcomeIndex, 0);
+  });
+
+  it("rejects a second vote from the same oracle", async () => {
+    try {
+      await submit(oracles[0], 2);
+      assert.fail("duplicate vote should be rejected");
+    } catch (err) {
+      // The vote PDA already exists, so its init fails
+      assert.match((err.logs ?? []).join("\n"), /already in use/);
+    }
+  });
+
+  it("flags conflicting votes", async () => {
+    const signature = await submit(oracles[1], 2);
+    const names = (await eventsOf(signature)).map((event) => event.name);
+
+    assert.include(names, "ConflictingVoteEvent");
+    assert.notInclude(names, "ResultProposedEvent");
+  });
+
+  it("proposes the result once M of N oracles agree", async () => {
+    const signature = await submit(oracles[2], 0);
+    const names = (await eventsOf(signature)).map((event) => event.name);
+    assert.include(names, "ResultProposedEvent");
+
+    // The result only becomes final after the dispute period
+    const state = await program.account.market.fetch(market);
+    assert.deepEqual(state.status, { proposed: {} });
+    assert.equal(state.proposedOutcome, 0);
+    assert.isNull(state.winningOutcome);
+    assert.deepEqual(
+      state.ballots.map((ballot) => ballot.outcomeIndex),
+      [0, 2, 0]
+    );
+  });
+
+  it("hands a split vote that cannot reach quorum to the arbitrator", async () => {
+    await submit(oracles[0], 0, splitMarket);
+    await submit(oracles[1], 1, splitMarket);
+    const signature = await submit(oracles[2], 2, splitMarket);
+    const names = (await eventsOf(signature)).map((event) => event.name);
+    assert.include(names, "QuorumDeadlockedEvent");
+
+    let state = await program.account.market.fetch(splitMarket);
+    assert.deepEqual(state.status, { deadlocked: {} });
+
+    await applyChange({ grantRole: { holder: authority, role: { arbitrator: {} } } });
+    await program.methods
+      .resolveDeadlock(1)
+      .accounts({ market: splitMarket, platform, roles, arbitrator: authority })
+      .rpc();
+
+    state = await program.account.market.fetch(splitMarket);
+    assert.deepEqual(state.status, { resolved: {} });
+    assert.equal(state.winningOutcome, 1);
+  });
+});